@@ -0,0 +1,146 @@
+//! Fake UPower owning org.freedesktop.UPower on the session bus, for trying
+//! the battery block with `backend: upower` and `bus: session` without a
+//! battery. Commands read from stdin change the DisplayDevice:
+//!
+//!   percentage <0-100>
+//!   state <charging|discharging|full|not-charging>
+//!   rate <watts>
+//!   ac <online|offline>
+//!
+//! The service goes away once stdin is closed. See samples/upower.sh
+
+use std::io::BufRead;
+use zbus::{blocking::connection, interface};
+
+const UPOWER_PATH: &str = "/org/freedesktop/UPower";
+const DEVICE_PATH: &str = "/org/freedesktop/UPower/devices/DisplayDevice";
+
+struct UPower {
+    on_battery: bool,
+}
+
+#[interface(name = "org.freedesktop.UPower")]
+impl UPower {
+    #[zbus(property)]
+    fn on_battery(&self) -> bool {
+        self.on_battery
+    }
+}
+
+struct Device {
+    percentage: f64,
+    state: u32,
+    energy_full: f64,
+    energy_rate: f64,
+}
+
+#[interface(name = "org.freedesktop.UPower.Device")]
+impl Device {
+    #[zbus(property)]
+    fn percentage(&self) -> f64 {
+        self.percentage
+    }
+
+    #[zbus(property)]
+    fn state(&self) -> u32 {
+        self.state
+    }
+
+    #[zbus(property)]
+    fn energy(&self) -> f64 {
+        self.energy_full * self.percentage / 100.0
+    }
+
+    #[zbus(property)]
+    fn energy_full(&self) -> f64 {
+        self.energy_full
+    }
+
+    #[zbus(property)]
+    fn energy_rate(&self) -> f64 {
+        self.energy_rate
+    }
+
+    #[zbus(property)]
+    fn is_present(&self) -> bool {
+        true
+    }
+}
+
+/// UPower device state of a name
+fn state(name: &str) -> Option<u32> {
+    match name {
+        "charging" => Some(1),
+        "discharging" => Some(2),
+        "full" => Some(4),
+        "not-charging" => Some(5),
+        _ => None,
+    }
+}
+
+fn apply(conn: &zbus::blocking::Connection, command: &str, arg: &str) -> zbus::Result<bool> {
+    let server = conn.object_server();
+    if command == "ac" {
+        let upower = server.interface::<_, UPower>(UPOWER_PATH)?;
+        let mut inner = upower.get_mut();
+        inner.on_battery = match arg {
+            "online" => false,
+            "offline" => true,
+            _ => return Ok(false),
+        };
+        zbus::block_on(inner.on_battery_changed(upower.signal_emitter()))?;
+        return Ok(true);
+    }
+
+    let device = server.interface::<_, Device>(DEVICE_PATH)?;
+    let mut inner = device.get_mut();
+    let emitter = device.signal_emitter();
+    match (command, arg.parse::<f64>().ok(), state(arg)) {
+        ("percentage", Some(percentage), _) => {
+            inner.percentage = percentage.clamp(0.0, 100.0);
+            zbus::block_on(inner.percentage_changed(emitter))?;
+            zbus::block_on(inner.energy_changed(emitter))?;
+        }
+        ("rate", Some(watts), _) => {
+            inner.energy_rate = watts.max(0.0);
+            zbus::block_on(inner.energy_rate_changed(emitter))?;
+        }
+        ("state", _, Some(state)) => {
+            inner.state = state;
+            zbus::block_on(inner.state_changed(emitter))?;
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+fn main() -> zbus::Result<()> {
+    let conn = connection::Builder::session()?
+        .name("org.freedesktop.UPower")?
+        .serve_at(UPOWER_PATH, UPower { on_battery: true })?
+        .serve_at(
+            DEVICE_PATH,
+            Device {
+                percentage: 75.0,
+                state: 2,
+                energy_full: 50.0,
+                energy_rate: 12.5,
+            },
+        )?
+        .build()?;
+    eprintln!("fake_upower: ready");
+
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let mut words = line.split_whitespace();
+        let (Some(command), Some(arg)) = (words.next(), words.next()) else {
+            continue;
+        };
+        if !apply(&conn, command, arg)? {
+            eprintln!("fake_upower: unknown command '{}'", line.trim());
+        }
+    }
+    Ok(())
+}
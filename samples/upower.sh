@@ -0,0 +1,21 @@
+#!/bin/sh
+# Run rstatus with samples/upower.yaml against examples/fake_upower on a
+# private session bus, no battery needed. Build both first:
+#
+#   cargo build --examples && samples/upower.sh
+#
+# Type fake_upower commands like 'percentage 40' or 'ac online' to see the
+# status line follow them. Closing stdin (Ctrl-D) stops the fake UPower,
+# the block turns invalid and rstatus is stopped a moment later.
+
+cd "$(dirname "$0")/.." || exit 1
+rstatus=${RSTATUS:-target/debug/rstatus}
+fake=${FAKE_UPOWER:-target/debug/examples/fake_upower}
+
+exec dbus-run-session -- sh -c '
+    "$1" -c samples/upower.yaml < /dev/null &
+    status=$!
+    "$2"
+    sleep 1
+    kill $status
+' sh "$rstatus" "$fake"
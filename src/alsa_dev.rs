@@ -6,13 +6,20 @@ use alsa::{
 
 use super::blocks::update_by_index;
 use super::sound_service::SoundService;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 use std::thread;
 
+const WAIT_TIMEOUT_MS: u32 = 1000;
+
 pub struct AlsaDevice {
     name: String,
     mixer: Mixer,
     hctl: Option<HCtl>,
     jack: Option<ElemId>,
+    alive: Arc<AtomicBool>,
 }
 
 impl AlsaDevice {
@@ -37,6 +44,7 @@ impl AlsaDevice {
                 mixer,
                 hctl: HCtl::new(card, false).ok(),
                 jack: None,
+                alive: Arc::new(AtomicBool::new(true)),
             };
             if let Some(ref hctl) = result.hctl {
                 result.jack = AlsaDevice::find_jack(hctl);
@@ -59,11 +67,22 @@ impl SoundService for AlsaDevice {
 
     fn listen(&mut self, block_index: usize) {
         if let Ok(mixer) = Mixer::new(&self.name, false) {
-            thread::spawn(move || loop {
-                if mixer.wait(None).is_ok() && mixer.handle_events().is_ok() {
-                    update_by_index(block_index);
-                } else {
-                    thread::sleep(std::time::Duration::from_secs(1));
+            let alive = self.alive.clone();
+            thread::spawn(move || {
+                // the wait times out now and then to notice the device is gone
+                while alive.load(Ordering::Acquire) {
+                    match mixer
+                        .wait(Some(WAIT_TIMEOUT_MS))
+                        .and_then(|_| mixer.handle_events())
+                    {
+                        Ok(0) => {}
+                        Ok(_) => {
+                            if alive.load(Ordering::Acquire) {
+                                update_by_index(block_index);
+                            }
+                        }
+                        Err(_) => thread::sleep(std::time::Duration::from_secs(1)),
+                    }
                 }
             });
         }
@@ -117,3 +136,9 @@ impl SoundService for AlsaDevice {
         self.mixer.handle_events().unwrap_or(0);
     }
 }
+
+impl Drop for AlsaDevice {
+    fn drop(&mut self) {
+        self.alive.store(false, Ordering::Release);
+    }
+}
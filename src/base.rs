@@ -1,14 +1,24 @@
 use super::block_builder::{BlockBuilder, RenderFlags, SubBlock};
-use super::utility::read_color;
+use super::click::{find_action, parse_on_click, ClickAction, ClickActions, ClickEvent};
+use super::config::Shorthand;
+use super::template::{self, Field, Numbers, Placeholders, Resolved, Units};
+use super::utility::{pango_escape, parse_duration, read_color};
+use serde::de::{Error, Unexpected, Visitor};
 use serde::{Deserialize, Deserializer};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::time::Duration;
 
 #[derive(Deserialize, Default)]
 pub enum Value {
     #[default]
     Invalid,
     Int(u32),
+    Float(f64),
     Str(String),
+    /// pango markup with the dynamic parts escaped already, only used when
+    /// the block has markup enabled
+    Markup(String),
 }
 
 pub trait ValueConstructor {
@@ -27,6 +37,18 @@ impl ValueConstructor for Option<u32> {
     }
 }
 
+impl ValueConstructor for f64 {
+    fn build(&self) -> Value {
+        Value::Float(*self)
+    }
+}
+
+impl ValueConstructor for Option<f64> {
+    fn build(&self) -> Value {
+        self.map_or(Value::Invalid, Value::Float)
+    }
+}
+
 impl ValueConstructor for String {
     fn build(&self) -> Value {
         Value::Str(self.clone())
@@ -63,30 +85,220 @@ impl Value {
     }
 }
 
-type Thresholds = BTreeMap<u32, u32>;
+/// attributes set along with the value by the update, on top of the
+/// configured ones
+#[derive(Default)]
+pub struct Attributes {
+    pub color: Option<u32>,
+    pub bgcolor: Option<u32>,
+    pub short_text: Option<String>,
+    pub urgent: bool,
+    pub prefix: Option<String>,
+    pub suffix: Option<String>,
+    /// number checked against thresholds when the value is a text
+    pub level: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum MinWidth {
+    Pixels(u32),
+    Sample(String),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+impl TextAlign {
+    fn name(&self) -> &'static str {
+        match self {
+            TextAlign::Left => "left",
+            TextAlign::Center => "center",
+            TextAlign::Right => "right",
+        }
+    }
+}
+
+/// either always or never urgent, or urgent from a value on
+pub enum Urgent {
+    Always(bool),
+    From(u32),
+}
+
+impl<'de> Deserialize<'de> for Urgent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct UrgentVisitor;
+
+        impl Visitor<'_> for UrgentVisitor {
+            type Value = Urgent;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("true, false or the value the block turns urgent from")
+            }
+
+            fn visit_bool<E: Error>(self, urgent: bool) -> Result<Urgent, E> {
+                Ok(Urgent::Always(urgent))
+            }
+
+            fn visit_u64<E: Error>(self, from: u64) -> Result<Urgent, E> {
+                u32::try_from(from)
+                    .map(Urgent::From)
+                    .map_err(|_| E::invalid_value(Unexpected::Unsigned(from), &self))
+            }
+        }
+
+        deserializer.deserialize_any(UrgentVisitor)
+    }
+}
+
+impl Default for Urgent {
+    fn default() -> Self {
+        Urgent::Always(false)
+    }
+}
 
+#[derive(Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Markup {
+    #[default]
+    None,
+    Pango,
+}
+
+#[derive(Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Pad {
+    #[default]
+    Space,
+    Zero,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UnitSystem {
+    #[default]
+    Iec,
+    Si,
+}
+
+/// what a threshold overrides from its lower bound on
+#[derive(Default, Clone)]
+pub struct Threshold {
+    color: Option<u32>,
+    bgcolor: Option<u32>,
+    prefix: Option<String>,
+    suffix: Option<String>,
+    urgent: Option<bool>,
+    invalid: Option<String>,
+}
+
+/// a threshold written as a map, a plain string is just its color
 #[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThresholdConfig {
+    color: Option<String>,
+    bgcolor: Option<String>,
+    prefix: Option<String>,
+    suffix: Option<String>,
+    urgent: Option<bool>,
+    invalid: Option<String>,
+}
+
+type Thresholds = BTreeMap<u32, Threshold>;
+
+/// decoration of a block in some state, see Base::set_status
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Status {
     #[serde(default)]
     pub prefix: String,
     #[serde(default)]
     pub suffix: String,
+    /// replaces the color of the block
+    #[serde(default = "default_none", deserialize_with = "parse_color_maybe")]
+    pub color: Option<u32>,
+    /// replace the thresholds of the block
+    #[serde(default, deserialize_with = "parse_thresholds_maybe")]
+    pub thresholds: Option<Thresholds>,
 }
 
+/// options kept by Base for the blocks running commands on update, only
+/// those blocks list them in their FIELDS, the others would ignore them
+pub const COMMAND_FIELDS: &[&str] = &["timeout", "stale_color"];
+
+/// options of Base, serde does not check them because of flatten
+pub const FIELDS: &[&str] = &[
+    "interval",
+    "align",
+    "signal",
+    "id",
+    "separator_width",
+    "custom_separator",
+    "color",
+    "bgcolor",
+    "prefix",
+    "prefix_color",
+    "suffix",
+    "suffix_color",
+    "invalid",
+    "invalid_color",
+    "threshold_fix",
+    "thresholds",
+    "on_click",
+    "short_text",
+    "min_width",
+    "text_align",
+    "urgent",
+    "border",
+    "border_top",
+    "border_right",
+    "border_bottom",
+    "border_left",
+    "markup",
+    "format",
+    "precision",
+    "width",
+    "pad",
+    "units",
+    "unit",
+    "min",
+    "max",
+    "gauge_glyphs",
+    "bar_glyphs",
+    "bar_width",
+    "history",
+];
+
 #[derive(Deserialize)]
 pub struct Base {
     /// value to display
     #[serde(skip)]
     pub value: Value,
-    /// interval for update block
-    #[serde(default = "default_zero")]
-    interval: u32,
+    #[serde(skip)]
+    pub attributes: Attributes,
+    /// interval for update block, zero if it is updated by signal only
+    #[serde(default, deserialize_with = "parse_interval")]
+    interval: Duration,
+    /// update on wall clock multiples of the interval
+    #[serde(default = "default_false")]
+    align: bool,
     /// signal for update block
     #[serde(default = "default_zero")]
     signal: u32,
     /// name of the block
     #[serde(skip)]
     name: String,
+    /// instance of the block, the index in config if not set
+    #[serde(default = "default_str_none")]
+    id: Option<String>,
     /// with of separator
     #[serde(default = "default_zero")]
     separator_width: u32,
@@ -124,9 +336,91 @@ pub struct Base {
     /// fix prefix and suffix color depend on threshold
     #[serde(default = "default_false")]
     threshold_fix: bool,
-    /// color thresholds
+    /// colors, texts and urgency depending on the value
     #[serde(default = "default_thresholds", deserialize_with = "parse_thresholds")]
     thresholds: Thresholds,
+    /// level of the last valid value, picks the invalid text
+    #[serde(skip)]
+    last_level: Option<u32>,
+    /// decoration of the current state of the block
+    #[serde(skip)]
+    status: Option<Status>,
+    /// commands run on clicks
+    #[serde(default, deserialize_with = "parse_on_click")]
+    on_click: ClickActions,
+    /// time limit of the commands run by update, zero for no limit
+    #[serde(default, deserialize_with = "parse_timeout")]
+    timeout: Duration,
+    /// color of the last good value while the update is late or timed out
+    #[serde(default = "default_none", deserialize_with = "parse_color_maybe")]
+    stale_color: Option<u32>,
+    #[serde(skip, default = "default_false")]
+    stale: bool,
+    /// short version of the block for a crowded bar, {} stands for the value
+    #[serde(default = "default_str_none")]
+    short_text: Option<String>,
+    /// minimal width of the value, in pixels or as wide as a sample text
+    #[serde(default)]
+    min_width: Option<MinWidth>,
+    /// alignment of the value within min_width
+    #[serde(default)]
+    text_align: Option<TextAlign>,
+    /// ask the bar to highlight the block
+    #[serde(default)]
+    urgent: Urgent,
+    /// border color, swaybar only
+    #[serde(default = "default_none", deserialize_with = "parse_color_maybe")]
+    border: Option<u32>,
+    /// border widths in pixels
+    #[serde(default)]
+    border_top: Option<u32>,
+    #[serde(default)]
+    border_right: Option<u32>,
+    #[serde(default)]
+    border_bottom: Option<u32>,
+    #[serde(default)]
+    border_left: Option<u32>,
+    /// pango lets prefix, suffix and formats carry markup
+    #[serde(default)]
+    markup: Markup,
+    /// layout of the value, blocks having a format option of their own
+    /// shadow it or hand it over with set_format
+    #[serde(default = "default_str_none")]
+    format: Option<String>,
+    #[serde(skip)]
+    pub placeholders: Placeholders,
+    /// digits after the decimal point of numbers and sizes
+    #[serde(default)]
+    precision: Option<usize>,
+    /// minimal width of the value in characters, numbers are right aligned
+    #[serde(default)]
+    width: usize,
+    #[serde(default)]
+    pad: Pad,
+    /// scale sizes by powers of 1024 or 1000
+    #[serde(default)]
+    units: UnitSystem,
+    /// fixed unit of sizes, like GiB or MB
+    #[serde(default, deserialize_with = "parse_unit")]
+    unit: Option<Units>,
+    /// range of the gauges
+    #[serde(default = "default_min")]
+    min: f64,
+    #[serde(default = "default_max")]
+    max: f64,
+    /// glyphs of the gauge and the sparkline, from the lowest to the highest
+    #[serde(default = "default_gauge_glyphs", deserialize_with = "parse_glyphs")]
+    gauge_glyphs: Vec<char>,
+    /// glyphs of a bar cell, from empty to full
+    #[serde(default = "default_bar_glyphs", deserialize_with = "parse_glyphs")]
+    bar_glyphs: Vec<char>,
+    #[serde(default = "default_bar_width")]
+    bar_width: usize,
+    /// number of samples in the sparkline
+    #[serde(default = "default_history")]
+    history: usize,
+    #[serde(skip)]
+    samples: VecDeque<f64>,
     #[serde(skip, default = "default_index")]
     index: usize,
 }
@@ -154,11 +448,23 @@ impl Base {
         }
     }
 
-    fn render_subblock(&self, out: &mut String, subblock: &SubBlock) {
+    fn render_subblock(
+        &self,
+        out: &mut String,
+        subblock: &SubBlock,
+        edges: (bool, bool),
+        urgent: bool,
+    ) {
         out.push_str(&format!(
             "{{\"full_text\":\"{}\",",
             json_escape(&subblock.text)
         ));
+        if subblock.short_text != subblock.text {
+            out.push_str(&format!(
+                "\"short_text\":\"{}\",",
+                json_escape(&subblock.short_text)
+            ));
+        }
         if subblock.flags & RenderFlags::Separator {
             out.push_str(&format!(
                 "\"separator_block_width\":{}",
@@ -168,27 +474,228 @@ impl Base {
             out.push_str("\"separator\":false,\"separator_block_width\":0");
         }
         Base::render_bg(out, self.bg());
-        if subblock.flags & RenderFlags::Name {
-            out.push_str(&format!(",\"name\":\"{}\"", self.name));
+        self.render_markup(out);
+        if urgent {
+            out.push_str(",\"urgent\":true");
         }
+        if subblock.holds_value {
+            match self.min_width {
+                Some(MinWidth::Pixels(width)) => out.push_str(&format!(",\"min_width\":{}", width)),
+                Some(MinWidth::Sample(ref sample)) => {
+                    out.push_str(&format!(",\"min_width\":\"{}\"", json_escape(sample)))
+                }
+                None => {}
+            }
+            if let Some(ref align) = self.text_align {
+                out.push_str(&format!(",\"align\":\"{}\"", align.name()));
+            }
+        }
+        self.render_border(out, edges);
+        out.push_str(&format!(
+            ",\"name\":\"{}\",\"instance\":\"{}\"",
+            self.name,
+            json_escape(&self.instance())
+        ));
         out.push_str(&format!(",\"color\":\"#{:06X}\"}}", subblock.color));
     }
 
-    fn get_to_color(&self, value: u32) -> u32 {
-        for (to, color) in self.thresholds.iter().rev() {
-            if value >= *to {
-                return *color;
+    /// the border wraps the whole block, so the inner sides of the subblocks
+    /// get no border
+    fn render_border(&self, out: &mut String, (first, last): (bool, bool)) {
+        let Some(color) = self.border else {
+            return;
+        };
+        // swaybar draws one pixel wide borders by default
+        let width = |width: Option<u32>, outer: bool| if outer { width.unwrap_or(1) } else { 0 };
+        out.push_str(&format!(
+            ",\"border\":\"#{:06X}\",\"border_top\":{},\"border_right\":{},\"border_bottom\":{},\"border_left\":{}",
+            color,
+            width(self.border_top, true),
+            width(self.border_right, last),
+            width(self.border_bottom, true),
+            width(self.border_left, first),
+        ));
+    }
+
+    fn render_markup(&self, out: &mut String) {
+        if self.markup == Markup::Pango {
+            out.push_str(",\"markup\":\"pango\"");
+        }
+    }
+
+    pub fn markup(&self) -> bool {
+        self.markup == Markup::Pango
+    }
+
+    /// escape the dynamic text if the block has markup enabled
+    fn escape(&self, text: &str) -> String {
+        if self.markup() {
+            pango_escape(text)
+        } else {
+            text.to_owned()
+        }
+    }
+
+    /// how the numbers of the block are shown
+    pub fn numbers(&self) -> Numbers {
+        Numbers {
+            precision: self.precision,
+            units: self.unit.unwrap_or(Units {
+                si: matches!(self.units, UnitSystem::Si),
+                power: None,
+            }),
+        }
+    }
+
+    fn value_field(&self) -> Option<Field> {
+        match self.value {
+            Value::Int(value) => Some(Field::from(value)),
+            Value::Float(value) => Some(Field::Number(value)),
+            Value::Str(ref text) => Some(Field::Text(text.clone())),
+            Value::Markup(ref text) => Some(Field::Raw(text.clone())),
+            Value::Invalid => None,
+        }
+    }
+
+    /// the value as a number, for the thresholds and the gauges
+    fn level(&self) -> Option<f64> {
+        match self.value {
+            Value::Int(value) => Some(value as f64),
+            Value::Float(value) => Some(value),
+            _ => self.attributes.level.map(|level| level as f64),
+        }
+    }
+
+    /// position of level within the range, from 0 to 1
+    fn fraction(&self, level: f64) -> f64 {
+        let range = self.max - self.min;
+        if range <= 0.0 {
+            return 0.0;
+        }
+        ((level - self.min) / range).clamp(0.0, 1.0)
+    }
+
+    fn gauge(&self, level: f64) -> char {
+        let last = self.gauge_glyphs.len() - 1;
+        self.gauge_glyphs[(self.fraction(level) * last as f64).round() as usize]
+    }
+
+    /// bar_width cells, the last filled one may be partially filled
+    fn bar(&self, level: f64) -> String {
+        let steps = self.bar_glyphs.len() - 1;
+        let filled = (self.fraction(level) * (self.bar_width * steps) as f64).round() as usize;
+        (0..self.bar_width)
+            .map(|cell| self.bar_glyphs[filled.saturating_sub(cell * steps).min(steps)])
+            .collect()
+    }
+
+    /// the level checked against the thresholds
+    fn threshold_level(&self) -> Option<u32> {
+        self.level()
+            .map(|level| level.round().clamp(0.0, u32::MAX as f64) as u32)
+    }
+
+    /// the attribute of the highest threshold at or below level which sets it
+    fn threshold<T>(
+        &self,
+        level: Option<u32>,
+        pick: impl Fn(&Threshold) -> Option<T>,
+    ) -> Option<T> {
+        let level = level?;
+        let thresholds = match self.status {
+            Some(Status {
+                thresholds: Some(ref thresholds),
+                ..
+            }) => thresholds,
+            _ => &self.thresholds,
+        };
+        thresholds
+            .range(..=level)
+            .rev()
+            .find_map(|(_, threshold)| pick(threshold))
+    }
+
+    /// remember the value for the sparkline, called after every update
+    pub fn sample(&mut self) {
+        if !matches!(self.value, Value::Invalid) {
+            self.last_level = self.threshold_level();
+        }
+        if self.history == 0 {
+            return;
+        }
+        if let (Some(level), false) = (self.level(), matches!(self.value, Value::Invalid)) {
+            if self.samples.len() == self.history {
+                self.samples.pop_front();
             }
+            self.samples.push_back(level);
+        }
+    }
+
+    fn resolve(&self, name: &str) -> Resolved {
+        let level = self
+            .level()
+            .filter(|_| !matches!(self.value, Value::Invalid));
+        let gauge = |render: &dyn Fn(f64) -> String| match level {
+            Some(level) => Resolved::Value(Field::Text(render(level))),
+            None => Resolved::Unresolved,
+        };
+        match name {
+            "" | "value" => self
+                .value_field()
+                .map_or(Resolved::Unresolved, Resolved::Value),
+            "gauge" => gauge(&|level| self.gauge(level).to_string()),
+            "bar" => gauge(&|level| self.bar(level)),
+            "sparkline" => Resolved::Value(Field::Text(
+                self.samples
+                    .iter()
+                    .map(|level| self.gauge(*level))
+                    .collect(),
+            )),
+            _ => template::lookup(&self.placeholders, name),
         }
-        self.color
+    }
+
+    /// the value as shown, laid out by format if there is one
+    fn value_text(&self) -> String {
+        if let Some(ref format) = self.format {
+            let resolve = |name: &str| self.resolve(name);
+            let mut budget = usize::MAX;
+            return template::render(format, &resolve, &mut budget, self.markup(), self.numbers());
+        }
+        match self.value_field() {
+            Some(field) => template::value(
+                field,
+                self.width,
+                self.pad == Pad::Zero,
+                self.markup(),
+                self.numbers(),
+            ),
+            None => String::new(),
+        }
+    }
+
+    fn urgent(&self, level: Option<u32>) -> bool {
+        self.attributes.urgent
+            || self.threshold(level, |threshold| threshold.urgent) == Some(true)
+            || match self.urgent {
+                Urgent::Always(urgent) => urgent,
+                Urgent::From(from) => level.is_some_and(|level| level >= from),
+            }
+    }
+
+    /// color of the value, the updates may replace the configured one
+    fn value_color(&self) -> u32 {
+        let status = self.status.as_ref().and_then(|status| status.color);
+        self.attributes.color.or(status).unwrap_or(self.color)
     }
 
     pub fn render(&self, out: &mut String, prev_bg: Option<u32>) {
-        if let (Some(sep), Some(bg)) = (self.custom_separator.as_ref(), self.bgcolor) {
+        if let (Some(sep), Some(bg)) = (self.custom_separator.as_ref(), self.bg()) {
             out.push_str(&format!("{{\"full_text\":\"{}\",\"separator\":false,\"separator_block_width\":0,\"color\":\"#{:06X}\"",
                    json_escape(sep),
                    bg));
             Base::render_bg(out, prev_bg);
+            self.render_markup(out);
             out.push_str("}},");
         }
 
@@ -201,70 +708,107 @@ impl Base {
                 }
         };
 
-        let subblocks = match self.value {
-            Value::Int(value) => {
-                let color = self.get_to_color(value);
+        let level = self.threshold_level();
+        let color = match self.value {
+            Value::Int(_) | Value::Float(_) | Value::Str(_) => Some(
+                self.threshold(level, |threshold| threshold.color)
+                    .unwrap_or(self.value_color()),
+            ),
+            Value::Markup(_) => Some(self.value_color()),
+            Value::Invalid => None,
+        };
+        let text = color.map(|color| (self.value_text(), color));
+
+        let mut subblocks = match text {
+            Some((ref text, color)) => {
                 let mut prefix_color = self.prefix_color.unwrap_or(color);
                 let mut suffix_color = self.suffix_color.unwrap_or(color);
-                if self.threshold_fix && color != self.color {
+                if self.threshold_fix && color != self.value_color() {
                     prefix_color = color;
                     suffix_color = color;
                 }
+                // the configured texts may carry markup, the dynamic ones not
+                let dynamic = |text: &Option<String>| text.as_deref().map(|text| self.escape(text));
+                let short_text = dynamic(&self.attributes.short_text);
+                let short_text = short_text.as_deref().unwrap_or(text);
+                let prefix = self.threshold(level, |threshold| threshold.prefix.clone());
+                let prefix = prefix.or(dynamic(&self.attributes.prefix));
+                let prefix = prefix.as_deref().unwrap_or(&self.prefix);
+                let suffix = self.threshold(level, |threshold| threshold.suffix.clone());
+                let suffix = suffix.or(dynamic(&self.attributes.suffix));
+                let suffix = suffix.as_deref().unwrap_or(&self.suffix);
                 BlockBuilder::new()
-                    .add(&self.prefix, prefix_color, RenderFlags::None)
+                    .add(prefix, prefix_color, RenderFlags::None)
                     .add(&self.iprefix, prefix_color, RenderFlags::None)
-                    .add(&value.to_string(), color, RenderFlags::Name)
+                    .add_value(text, short_text, color, RenderFlags::None)
                     .add(&self.isuffix, suffix_color, suffix_flags(RenderFlags::None))
-                    .add(&self.suffix, suffix_color, suffix_flags(RenderFlags::None))
+                    .add(suffix, suffix_color, suffix_flags(RenderFlags::None))
+                    .get()
+            }
+            None => {
+                let invalid =
+                    self.threshold(self.last_level, |threshold| threshold.invalid.clone());
+                BlockBuilder::new()
+                    .add(
+                        invalid.as_deref().unwrap_or(&self.invalid),
+                        self.invalid_color,
+                        suffix_flags(RenderFlags::None),
+                    )
                     .get()
             }
-            Value::Str(ref value) => BlockBuilder::new()
-                .add(
-                    &self.prefix,
-                    self.prefix_color.unwrap_or(self.color),
-                    RenderFlags::None,
-                )
-                .add(
-                    &self.iprefix,
-                    self.prefix_color.unwrap_or(self.color),
-                    RenderFlags::None,
-                )
-                .add(value, self.color, RenderFlags::Name)
-                .add(
-                    &self.isuffix,
-                    self.suffix_color.unwrap_or(self.color),
-                    suffix_flags(RenderFlags::None),
-                )
-                .add(
-                    &self.suffix,
-                    self.suffix_color.unwrap_or(self.color),
-                    suffix_flags(RenderFlags::None),
-                )
-                .get(),
-            Value::Invalid => BlockBuilder::new()
-                .add(
-                    &self.invalid,
-                    self.invalid_color,
-                    suffix_flags(RenderFlags::Name),
-                )
-                .get(),
         };
 
+        let invalid = matches!(self.value, Value::Invalid);
+        if let (true, Some(color), false) = (self.stale, self.stale_color, invalid) {
+            for sb in subblocks.iter_mut() {
+                sb.color = color;
+            }
+        }
+
+        // the configured short text stands for the whole block
+        if let (Some(short), Some((text, _))) = (self.short_text.as_ref(), text.as_ref()) {
+            let value = match self.attributes.short_text {
+                Some(ref short_text) => self.escape(short_text),
+                None => text.clone(),
+            };
+            for (index, sb) in subblocks.iter_mut().enumerate() {
+                sb.short_text = if index == 0 {
+                    short.replace("{}", &value)
+                } else {
+                    String::new()
+                };
+            }
+        }
+
+        let urgent = self.urgent(level);
+        let count = subblocks.len();
         let mut first = true;
-        for sb in subblocks.iter() {
+        for (index, sb) in subblocks.iter().enumerate() {
             if !first {
                 out.push(',');
             } else {
                 first = false
             }
-            self.render_subblock(out, sb);
+            self.render_subblock(out, sb, (index == 0, index + 1 == count), urgent);
         }
     }
 
-    pub fn interval(&self) -> u32 {
+    pub fn interval(&self) -> Duration {
         self.interval
     }
 
+    pub fn align(&self) -> bool {
+        self.align
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        (!self.timeout.is_zero()).then_some(self.timeout)
+    }
+
+    pub fn set_stale(&mut self, stale: bool) {
+        self.stale = stale;
+    }
+
     pub fn signal(&self) -> u32 {
         self.signal
     }
@@ -274,7 +818,29 @@ impl Base {
     }
 
     pub fn bg(&self) -> Option<u32> {
-        self.bgcolor
+        let level = match self.value {
+            Value::Invalid => None,
+            _ => self.threshold_level(),
+        };
+        self.threshold(level, |threshold| threshold.bgcolor)
+            .or(self.attributes.bgcolor)
+            .or(self.bgcolor)
+    }
+
+    /// decorate the block for its current state, None drops the decoration
+    /// of the previous one
+    pub fn set_status(&mut self, status: Option<&Status>) {
+        self.iprefix = status
+            .map(|status| status.prefix.clone())
+            .unwrap_or_default();
+        self.isuffix = status
+            .map(|status| status.suffix.clone())
+            .unwrap_or_default();
+        self.status = status.cloned();
+    }
+
+    pub fn set_format(&mut self, format: String) {
+        self.format = Some(format);
     }
 
     pub fn set_prefix(&mut self, prefix: &str) {
@@ -296,6 +862,18 @@ impl Base {
     pub fn index(&self) -> usize {
         self.index
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn instance(&self) -> String {
+        self.id.clone().unwrap_or_else(|| self.index.to_string())
+    }
+
+    pub fn action(&self, event: &ClickEvent) -> Option<ClickAction> {
+        find_action(&self.on_click, event).cloned()
+    }
 }
 
 fn parse_color<'de, D>(deserializer: D) -> Result<u32, D::Error>
@@ -320,18 +898,119 @@ where
     Ok(None)
 }
 
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IntervalConfig {
+    Seconds(u64),
+    Fraction(f64),
+    Text(String),
+}
+
+/// shortest update period, anything below is closer to a busy loop than to
+/// a status line
+const MIN_INTERVAL: Duration = Duration::from_millis(10);
+
+/// longest interval or timeout, deadlines that far away are never reached
+/// anyway, while much larger ones overflow the clock
+const MAX_DURATION: Duration = Duration::from_secs(24 * 3600);
+
+fn parse_timeout<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let duration = match IntervalConfig::deserialize(deserializer)? {
+        IntervalConfig::Seconds(seconds) => Duration::from_secs(seconds),
+        IntervalConfig::Fraction(seconds) => {
+            Duration::try_from_secs_f64(seconds).map_err(D::Error::custom)?
+        }
+        IntervalConfig::Text(text) => parse_duration(&text).map_err(D::Error::custom)?,
+    };
+    if duration > MAX_DURATION {
+        return Err(D::Error::custom(format!(
+            "{:?} is too long, the maximum is 24h",
+            duration
+        )));
+    }
+    Ok(duration)
+}
+
+fn parse_interval<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let interval = parse_timeout(deserializer)?;
+    if !interval.is_zero() && interval < MIN_INTERVAL {
+        return Err(D::Error::custom(format!(
+            "{:?} is too short, the interval must be at least {:?}",
+            interval, MIN_INTERVAL
+        )));
+    }
+    Ok(interval)
+}
+
+fn parse_unit<'de, D>(deserializer: D) -> Result<Option<Units>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let name = String::deserialize(deserializer)?;
+    match Units::parse(&name) {
+        Some(units) => Ok(Some(units)),
+        None => Err(D::Error::custom(format!("unknown unit {}", name))),
+    }
+}
+
+fn parse_glyphs<'de, D>(deserializer: D) -> Result<Vec<char>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let glyphs: Vec<char> = String::deserialize(deserializer)?.chars().collect();
+    if glyphs.len() < 2 {
+        return Err(D::Error::custom("at least two glyphs are needed"));
+    }
+    Ok(glyphs)
+}
+
 fn parse_thresholds<'de, D>(deserializer: D) -> Result<Thresholds, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let ms: BTreeMap<u32, String> = BTreeMap::deserialize(deserializer)?;
+    let ms: BTreeMap<u32, Shorthand<ThresholdConfig>> = BTreeMap::deserialize(deserializer)?;
     let mut result = Thresholds::new();
-    for (threshold, color) in ms {
-        result.insert(threshold, read_color(&color, 0));
+    for (bound, threshold) in ms {
+        let color = |color: Option<String>| color.map(|color| read_color(&color, 0));
+        let threshold = match threshold {
+            Shorthand::Short(text) => Threshold {
+                color: Some(read_color(&text, 0)),
+                ..Default::default()
+            },
+            Shorthand::Full(ThresholdConfig {
+                color: text,
+                bgcolor,
+                prefix,
+                suffix,
+                urgent,
+                invalid,
+            }) => Threshold {
+                color: color(text),
+                bgcolor: color(bgcolor),
+                prefix,
+                suffix,
+                urgent,
+                invalid,
+            },
+        };
+        result.insert(bound, threshold);
     }
     Ok(result)
 }
 
+fn parse_thresholds_maybe<'de, D>(deserializer: D) -> Result<Option<Thresholds>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    parse_thresholds(deserializer).map(Some)
+}
+
 fn default_invalid() -> String {
     "invalid".to_string()
 }
@@ -360,6 +1039,30 @@ pub fn default_str_none() -> Option<String> {
     None
 }
 
+fn default_min() -> f64 {
+    0.0
+}
+
+fn default_max() -> f64 {
+    100.0
+}
+
+fn default_gauge_glyphs() -> Vec<char> {
+    "▁▂▃▄▅▆▇█".chars().collect()
+}
+
+fn default_bar_glyphs() -> Vec<char> {
+    "-#".chars().collect()
+}
+
+fn default_bar_width() -> usize {
+    8
+}
+
+fn default_history() -> usize {
+    10
+}
+
 fn default_thresholds() -> Thresholds {
     Thresholds::new()
 }
@@ -1,78 +1,437 @@
-use super::base::{default_str_none, default_zero, Base, Status, Value};
+use super::base::{default_false, default_str_none, default_zero, Base, Status, Value};
 use super::block;
+use super::template::Field;
+use super::upower_dev::{Bus, UPowerService};
+use super::utility::matches;
+use super::worker::{self, Worker};
 use serde::Deserialize;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+
+/// options of the block, Base ones excluded
+pub const FIELDS: &[&str] = &[
+    "sensor",
+    "statuses",
+    "warning_level",
+    "warning_action",
+    "warnings",
+    "warning_hysteresis",
+    "smoothing",
+    "backend",
+    "device",
+    "bus",
+    "timeout",
+];
+
+const POWER_SUPPLY: &str = "/sys/class/power_supply";
+
+/// power supply directories, their names may be masks like BAT*
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Sensors {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// where the battery state comes from
+#[derive(Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Backend {
+    #[default]
+    Sysfs,
+    /// updated as soon as UPower reports a change
+    Upower,
+}
+
+/// what happens once the capacity drops below level while discharging
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Warning {
+    level: u32,
+    #[serde(default = "default_str_none")]
+    action: Option<String>,
+    /// highlight the block while below level
+    #[serde(default = "default_false")]
+    urgent: bool,
+    /// the action ran in this discharge cycle already
+    #[serde(skip)]
+    fired: bool,
+    #[serde(skip)]
+    worker: Worker<()>,
+}
 
 #[derive(Deserialize)]
 pub struct Block {
     #[serde(flatten)]
     base: Base,
-    sensor: String,
+    /// every battery of the system if not set
+    #[serde(default)]
+    sensor: Option<Sensors>,
     #[serde(default = "default_statuses")]
     statuses: BTreeMap<String, Status>,
     #[serde(default = "default_zero")]
     warning_level: u32,
     #[serde(default = "default_str_none")]
     warning_action: Option<String>,
+    #[serde(default)]
+    warnings: Vec<Warning>,
+    /// how far the capacity has to climb past a level to fire it again
+    #[serde(default = "default_hysteresis")]
+    warning_hysteresis: u32,
+    /// number of power readings averaged for the time left
+    #[serde(default = "default_smoothing")]
+    smoothing: usize,
+    /// the last power readings in watts, of the current status only
+    #[serde(skip)]
+    power: VecDeque<f64>,
+    #[serde(skip)]
+    last_status: String,
+    #[serde(default)]
+    backend: Backend,
+    /// UPower device, the combination of all batteries by default
+    #[serde(default = "default_device")]
+    device: String,
+    #[serde(default)]
+    bus: Bus,
+    #[serde(skip)]
+    service: Option<UPowerService>,
+}
+
+/// state of a single battery
+struct Reading {
+    status: String,
+    capacity: Option<u32>,
+    /// energy in watt hours
+    now: Option<f64>,
+    full: Option<f64>,
+    watts: Option<f64>,
+}
+
+fn read_u64(sensor: &Path, name: &str) -> Option<u64> {
+    std::fs::read_to_string(sensor.join(name))
+        .ok()
+        .and_then(|text| text.trim().parse::<u64>().ok())
+}
+
+/// the sysfs values are in micro units, negative currents are reported by
+/// some batteries while discharging
+fn read_micro(sensor: &Path, name: &str) -> Option<f64> {
+    std::fs::read_to_string(sensor.join(name))
+        .ok()
+        .and_then(|text| text.trim().parse::<i64>().ok())
+        .map(|value| value.unsigned_abs() as f64 / 1e6)
+}
+
+fn read_capacity(sensor: &Path) -> Option<u32> {
+    if let Some(capacity) = read_u64(sensor, "capacity") {
+        return Some(capacity.min(100) as u32);
+    }
+
+    for (now, full) in [("energy_now", "energy_full"), ("charge_now", "charge_full")] {
+        if let (Some(now), Some(full)) = (read_u64(sensor, now), read_u64(sensor, full)) {
+            #[allow(clippy::manual_checked_ops)]
+            if full > 0 {
+                return Some((((now * 100 + full / 2) / full).min(100)) as u32);
+            }
+        }
+    }
+
+    None
+}
+
+fn read_watts(sensor: &Path) -> Option<f64> {
+    read_micro(sensor, "power_now")
+        .or_else(|| Some(read_micro(sensor, "current_now")? * read_micro(sensor, "voltage_now")?))
+}
+
+/// energy in watt hours, computed from the charge if the battery reports amp
+/// hours
+fn read_energy(sensor: &Path, what: &str) -> Option<f64> {
+    read_micro(sensor, &format!("energy_{}", what)).or_else(|| {
+        Some(read_micro(sensor, &format!("charge_{}", what))? * read_micro(sensor, "voltage_now")?)
+    })
+}
+
+fn read(sensor: &Path) -> Option<Reading> {
+    let status = std::fs::read_to_string(sensor.join("status")).ok()?;
+    Some(Reading {
+        status: status.trim().to_owned(),
+        capacity: read_capacity(sensor),
+        now: read_energy(sensor, "now"),
+        full: read_energy(sensor, "full"),
+        // an idle battery reports no power at all
+        watts: read_watts(sensor).filter(|watts| *watts > 0.0),
+    })
+}
+
+/// the directories matching the mask, a glob in the last component only
+fn expand(mask: &str) -> Vec<PathBuf> {
+    let path = Path::new(mask);
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return vec![path.to_owned()];
+    };
+    let name = name.to_string_lossy();
+    if !name.contains(['*', '?']) {
+        return vec![path.to_owned()];
+    }
+    let mut result: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| matches(&name, &entry.file_name().to_string_lossy()))
+                .map(|entry| entry.path())
+                .collect()
+        })
+        .unwrap_or_default();
+    result.sort();
+    result
+}
+
+/// system batteries, peripherals like mice report a device scope
+fn discover() -> Vec<PathBuf> {
+    let read = |dir: &Path, name: &str| {
+        std::fs::read_to_string(dir.join(name))
+            .map(|text| text.trim().to_owned())
+            .unwrap_or_default()
+    };
+    let mut result: Vec<PathBuf> = std::fs::read_dir(POWER_SUPPLY)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|dir| read(dir, "type") == "Battery" && read(dir, "scope") != "Device")
+                .collect()
+        })
+        .unwrap_or_default();
+    result.sort();
+    result
+}
+
+/// the AC line is plugged, None if there is no adapter to tell
+fn read_ac() -> Option<bool> {
+    let read = |dir: &Path, name: &str| {
+        std::fs::read_to_string(dir.join(name))
+            .map(|text| text.trim().to_owned())
+            .unwrap_or_default()
+    };
+    let adapters: Vec<bool> = std::fs::read_dir(POWER_SUPPLY)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|dir| matches!(read(dir, "type").as_str(), "Mains" | "USB"))
+        .map(|dir| read(&dir, "online") == "1")
+        .collect();
+    (!adapters.is_empty()).then(|| adapters.contains(&true))
+}
+
+/// the status of the batteries as a whole: one charging or discharging is
+/// enough, while the others are usually idle
+fn combined_status(readings: &[Reading]) -> &str {
+    for status in ["Charging", "Discharging"] {
+        if readings.iter().any(|reading| reading.status == status) {
+            return status;
+        }
+    }
+    if readings.iter().all(|reading| reading.status == "Full") {
+        return "Full";
+    }
+    &readings[0].status
+}
+
+/// the sum of a reading of every battery, None if some battery lacks it
+fn total(readings: &[Reading], value: impl Fn(&Reading) -> Option<f64>) -> Option<f64> {
+    readings.iter().map(value).sum()
+}
+
+/// capacity weighted by the energy of the batteries, their average if some
+/// battery does not report energy
+fn combined_capacity(readings: &[Reading]) -> Option<u32> {
+    if let [reading] = readings {
+        return reading.capacity;
+    }
+    if let (Some(now), Some(full)) = (
+        total(readings, |reading| reading.now),
+        total(readings, |reading| reading.full),
+    ) {
+        if full > 0.0 {
+            return Some(((now / full * 100.0).round() as u32).min(100));
+        }
+    }
+    let capacities: Vec<u32> = readings
+        .iter()
+        .filter_map(|reading| reading.capacity)
+        .collect();
+    let sum: u32 = capacities.iter().sum();
+    sum.checked_div(capacities.len() as u32)
 }
 
 impl Block {
-    fn read_u64(&self, name: &str) -> Result<u64, ()> {
-        std::fs::read_to_string(self.sensor.clone() + "/" + name)
-            .map_err(|_| ())
-            .and_then(|text| text.trim().parse::<u64>().map_err(|_| ()))
+    fn sensors(&self) -> Vec<PathBuf> {
+        match self.sensor {
+            Some(Sensors::One(ref mask)) => expand(mask),
+            Some(Sensors::Many(ref masks)) => masks.iter().flat_map(|mask| expand(mask)).collect(),
+            None => discover(),
+        }
     }
 
-    fn read_capacity(&self) -> Result<u32, ()> {
-        if let Ok(capacity) = self.read_u64("capacity") {
-            return Ok(capacity.min(100) as u32);
+    /// the batteries and the AC line
+    fn read(&mut self) -> (Vec<Reading>, Option<bool>) {
+        if self.backend == Backend::Sysfs {
+            let readings = self
+                .sensors()
+                .iter()
+                .filter_map(|sensor| read(sensor))
+                .collect();
+            return (readings, read_ac());
         }
 
-        for (now, full) in [("energy_now", "energy_full"), ("charge_now", "charge_full")] {
-            if let (Ok(now), Ok(full)) = (self.read_u64(now), self.read_u64(full)) {
-                if full > 0 {
-                    return Ok((((now * 100 + full / 2) / full).min(100)) as u32);
-                }
+        let service = self
+            .service
+            .get_or_insert_with(|| UPowerService::new(self.base.index(), self.bus, &self.device));
+        let Some(device) = service.device() else {
+            return (Vec::new(), None);
+        };
+        let energy = (device.energy_full > 0.0).then_some(());
+        let reading = Reading {
+            status: device.status.to_owned(),
+            capacity: Some(device.percentage.round().clamp(0.0, 100.0) as u32),
+            now: energy.map(|_| device.energy),
+            full: energy.map(|_| device.energy_full),
+            watts: Some(device.energy_rate).filter(|watts| *watts > 0.0),
+        };
+        (
+            vec![reading],
+            device.on_battery.map(|on_battery| !on_battery),
+        )
+    }
+
+    /// run the actions of the levels crossed, each one once per discharge
+    /// cycle, returns true if the block has to be urgent
+    fn warn(&mut self, status: &str, capacity: Option<u32>) -> bool {
+        // warning_level and warning_action are a single warning
+        if let Some(action) = self.warning_action.take() {
+            self.warnings.push(Warning {
+                level: self.warning_level,
+                action: Some(action),
+                urgent: false,
+                fired: false,
+                worker: Worker::default(),
+            });
+        }
+
+        let discharging = status == "Discharging";
+        let mut urgent = false;
+        for warning in self.warnings.iter_mut() {
+            let Some(capacity) = capacity else {
+                continue;
+            };
+            if !discharging || capacity >= warning.level + self.warning_hysteresis {
+                warning.fired = false;
+            }
+            if !discharging || capacity >= warning.level {
+                continue;
             }
+            urgent |= warning.urgent;
+            if warning.fired {
+                continue;
+            }
+            warning.fired = match warning.action {
+                // a single status line asked for by a script is no reason to
+                // act, the action may well suspend the machine
+                Some(_) if worker::inline() => true,
+                Some(ref action) => {
+                    let action = action.clone();
+                    let timeout = self.base.timeout();
+                    // retried on the next update if the last run is not over yet
+                    let (instance, name) = (self.base.instance(), self.base.name().to_owned());
+                    warning.worker.start(instance, name, move || {
+                        let _ = worker::run(&action, &Vec::new(), timeout);
+                    })
+                }
+                None => true,
+            };
         }
+        urgent
+    }
 
-        Err(())
+    /// average power over the last readings, they start over once the status
+    /// changes since charging and discharging rates have nothing in common
+    fn smooth_power(&mut self, status: &str, watts: Option<f64>) -> Option<f64> {
+        if status != self.last_status {
+            self.power.clear();
+            self.last_status = status.to_owned();
+        }
+        if let Some(watts) = watts {
+            if self.power.len() >= self.smoothing.max(1) {
+                self.power.pop_front();
+            }
+            self.power.push_back(watts);
+        }
+        (!self.power.is_empty()).then(|| self.power.iter().sum::<f64>() / self.power.len() as f64)
     }
 }
 
+/// hours until the batteries are empty while discharging, or full while
+/// charging
+fn time_left(readings: &[Reading], status: &str, watts: f64) -> Option<f64> {
+    let now = total(readings, |reading| reading.now)?;
+    let left = match status {
+        "Discharging" => now,
+        "Charging" => (total(readings, |reading| reading.full)? - now).max(0.0),
+        _ => return None,
+    };
+    Some(left / watts)
+}
+
+/// hours as h:mm
+fn format_hours(hours: f64) -> String {
+    let minutes = (hours * 60.0).round() as u64;
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
 impl block::Block for Block {
     impl_Block!();
 
     fn update(&mut self) {
-        let status_raw = std::fs::read_to_string(self.sensor.clone() + "/status")
-            .unwrap_or_else(|_| "error".to_string());
-
-        let status = status_raw.trim();
-        if status == "error" {
+        let (readings, ac) = self.read();
+        if readings.is_empty() {
+            self.base.attributes.urgent = false;
             self.base.value = Value::Invalid;
             return;
         }
 
-        let decoration = self.statuses.get(status2rstatus(status));
-        if let Some(d) = decoration {
-            self.base.set_prefix(&d.prefix);
-            self.base.set_suffix(&d.suffix);
-        }
+        let status = combined_status(&readings).to_owned();
+        self.base.set_status(status_entry(&self.statuses, &status));
 
-        let value = self.read_capacity();
-
-        if let Some(ref action) = self.warning_action {
-            if status == "Discharging" {
-                if let Ok(v) = value {
-                    if v < self.warning_level {
-                        let _ = std::process::Command::new("sh")
-                            .arg("-c")
-                            .arg(action)
-                            .status();
-                    }
-                }
-            }
+        let value = combined_capacity(&readings);
+        self.base.attributes.urgent = self.warn(&status, value);
+
+        // idle batteries draw no power, the others add up
+        let watts = readings
+            .iter()
+            .filter_map(|reading| reading.watts)
+            .sum::<f64>();
+        let power = self.smooth_power(&status, (watts > 0.0).then_some(watts));
+        let time_left = power.and_then(|watts| time_left(&readings, &status, watts));
+        self.base.placeholders = vec![
+            ("status", Field::from(status.as_str())),
+            (
+                "time_left",
+                Field::from(time_left.map(format_hours).unwrap_or_default()),
+            ),
+            (
+                "ac",
+                Field::from(ac.map_or("", |ac| if ac { "online" } else { "offline" })),
+            ),
+        ];
+        if let Some(capacity) = value {
+            self.base
+                .placeholders
+                .push(("capacity", Field::from(capacity)));
         }
+        // an unknown power is left out of the groups rather than shown verbatim
+        let power = power.map_or(Field::from(""), Field::from);
+        self.base.placeholders.push(("power", power));
         self.base.value = Value::new(value);
     }
 }
@@ -81,6 +440,34 @@ fn default_statuses() -> BTreeMap<String, Status> {
     BTreeMap::new()
 }
 
+fn default_device() -> String {
+    "DisplayDevice".to_owned()
+}
+
+fn default_hysteresis() -> u32 {
+    3
+}
+
+fn default_smoothing() -> usize {
+    5
+}
+
+/// the entry of statuses for the kernel status, named like not_charging,
+/// falling back to the online, offline and full ones
+fn status_entry<'a>(statuses: &'a BTreeMap<String, Status>, status: &str) -> Option<&'a Status> {
+    let key = status_key(status);
+    statuses
+        .iter()
+        .find(|(name, _)| status_key(name) == key)
+        .map(|(_, entry)| entry)
+        .or_else(|| statuses.get(status2rstatus(status)))
+}
+
+/// "Not charging" and not_charging name the same status
+fn status_key(status: &str) -> String {
+    status.to_lowercase().replace(' ', "_")
+}
+
 fn status2rstatus(status: &str) -> &str {
     match status {
         "Charging" => "online",
@@ -1,18 +1,43 @@
+use super::click::{ClickAction, ClickEvent};
+use std::time::Duration;
+
 pub trait Block {
     fn update(&mut self);
     fn set_name(&mut self, name: String);
-    fn interval(&self) -> u32;
+    fn name(&self) -> &str;
+    fn instance(&self) -> String;
+    fn interval(&self) -> Duration;
+    fn align(&self) -> bool;
     fn signal(&self) -> u32;
     fn bgcolor(&self) -> Option<u32>;
     fn render(&self, out: &mut String, prev_bg: Option<u32>);
     fn set_index(&mut self, index: usize);
+    fn action(&self, event: &ClickEvent) -> Option<ClickAction>;
+    /// take the results of the background work started by update, returns
+    /// true if they set the value
+    fn finish(&mut self) -> bool {
+        false
+    }
+    /// the value is set by finish, update only starts the work
+    fn deferred(&self) -> bool {
+        false
+    }
+    /// keep the value in the history, called once for every new value
+    fn sample(&mut self);
+    /// handle a click on the block, returns true if the block has to be redrawn
+    fn click(&mut self, _event: &ClickEvent) -> bool {
+        false
+    }
 }
 
 macro_rules! impl_Block {
     () => {
-        fn interval(&self) -> u32 {
+        fn interval(&self) -> std::time::Duration {
             self.base.interval()
         }
+        fn align(&self) -> bool {
+            self.base.align()
+        }
         fn signal(&self) -> u32 {
             self.base.signal()
         }
@@ -22,11 +47,23 @@ macro_rules! impl_Block {
         fn set_name(&mut self, name: String) {
             self.base.set_name(name);
         }
+        fn name(&self) -> &str {
+            self.base.name()
+        }
+        fn instance(&self) -> String {
+            self.base.instance()
+        }
         fn bgcolor(&self) -> Option<u32> {
             self.base.bg()
         }
         fn set_index(&mut self, index: usize) {
             self.base.set_index(index);
         }
+        fn action(&self, event: &crate::click::ClickEvent) -> Option<crate::click::ClickAction> {
+            self.base.action(event)
+        }
+        fn sample(&mut self) {
+            self.base.sample();
+        }
     };
 }
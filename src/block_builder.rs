@@ -6,16 +6,12 @@ use std::ops::{BitAnd, BitOr, BitOrAssign};
 pub enum RenderFlags {
     None = 0,
     Separator = 1,
-    Name = 2,
-    Both = 3,
 }
 
 impl From<u8> for RenderFlags {
     fn from(value: u8) -> Self {
         match value {
             1 => RenderFlags::Separator,
-            2 => RenderFlags::Name,
-            3 => RenderFlags::Both,
             _ => RenderFlags::None,
         }
     }
@@ -49,16 +45,22 @@ impl BitAnd for RenderFlags {
 
 pub struct SubBlock {
     pub text: String,
+    /// text shown when the bar runs out of space
+    pub short_text: String,
     pub color: u32,
     pub flags: RenderFlags,
+    /// the value is a part of the text
+    pub holds_value: bool,
 }
 
 impl SubBlock {
-    fn new(text: &str, color: u32, flags: RenderFlags) -> Self {
+    fn new(text: &str, short_text: &str, color: u32, flags: RenderFlags) -> Self {
         SubBlock {
             text: text.to_owned(),
+            short_text: short_text.to_owned(),
             color,
             flags,
+            holds_value: false,
         }
     }
 }
@@ -74,17 +76,31 @@ impl BlockBuilder {
         }
     }
 
-    pub fn add(mut self, text: &str, color: u32, flags: RenderFlags) -> Self {
+    pub fn add(self, text: &str, color: u32, flags: RenderFlags) -> Self {
+        self.add_text(text, text, color, flags)
+    }
+
+    pub fn add_value(self, text: &str, short_text: &str, color: u32, flags: RenderFlags) -> Self {
+        let mut result = self.add_text(text, short_text, color, flags);
+        if let Some(last) = result.sub_blocks.last_mut() {
+            last.holds_value = true;
+        }
+        result
+    }
+
+    fn add_text(mut self, text: &str, short_text: &str, color: u32, flags: RenderFlags) -> Self {
         let last_exists = self.sub_blocks.iter_mut().last();
 
         if let Some(last) = last_exists {
             if color == last.color {
                 last.text += text;
+                last.short_text += short_text;
                 last.flags |= flags;
                 return self;
             }
         }
-        self.sub_blocks.push(SubBlock::new(text, color, flags));
+        self.sub_blocks
+            .push(SubBlock::new(text, short_text, color, flags));
         self
     }
 
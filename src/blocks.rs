@@ -1,6 +1,11 @@
-use super::block::Block;
+use super::base::{Base, Value};
+use super::block::{self, Block};
+use super::click::ClickEvent;
+use super::config::{self, ConfigError};
+use super::{scheduler, utility};
 use serde::Deserialize;
-use std::sync::{Arc, Mutex, Once};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Once, OnceLock};
 
 macro_rules! all_blocks {
     ($mac:ident) => {
@@ -45,6 +50,15 @@ macro_rules! define_blocks {
              $($name($name::Block)),+
          }
 
+         const TAGS: &[&str] = &[$(stringify!($name)),+];
+
+         fn fields(tag: &str) -> Option<&'static [&'static str]> {
+             match tag {
+                 $(stringify!($name) => Some($name::FIELDS),)+
+                 _ => None,
+             }
+         }
+
          fn to_box(b: Blocks) -> Box<dyn Block> {
             match b {
                 $(Blocks::$name(mut v) => {
@@ -76,27 +90,133 @@ pub fn blocks() -> BlocksWrapper {
     }
 }
 
-fn init_from_config(config_path: &std::path::Path) -> BlocksCollection {
-    let data = std::fs::read_to_string(config_path).expect("cannot find config file");
-    let mut vals: Vec<Blocks> = serde_yaml::from_str(&data).expect("failed parse config file");
+static CONFIG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// set the config path, must be called before the first blocks() call
+pub fn set_config(path: PathBuf) {
+    CONFIG_PATH.set(path).expect("config path is already set");
+}
+
+/// block standing in for the whole config when it cannot be loaded
+struct Failure {
+    base: Base,
+}
+
+impl Failure {
+    fn new(message: String) -> Self {
+        // defaults of every option, redisplayed once in a while to keep the
+        // bar alive since there is nothing to update
+        let mut base: Base = serde_yaml::from_str("interval: 60").expect("valid base");
+        base.set_color(0xff0000);
+        base.value = Value::new(message);
+        Failure { base }
+    }
+}
+
+impl block::Block for Failure {
+    impl_Block!();
+    fn update(&mut self) {}
+}
+
+pub fn load(config_path: &Path) -> Result<BlocksCollection, ConfigError> {
+    let data = std::fs::read_to_string(config_path)
+        .map_err(|e| ConfigError::new(format!("cannot read config: {}", e)))?;
+    config::validate(&data, TAGS, fields)?;
+    let mut vals: Vec<Blocks> =
+        serde_yaml::from_str(&data).map_err(|e| config::from_yaml(&data, &e))?;
     let mut result: BlocksCollection = vals.drain(..).map(to_box).collect();
     for (index, block) in result.iter_mut().enumerate() {
         block.set_index(index);
     }
-    result
+    for (index, block) in result.iter().enumerate() {
+        let instance = block.instance();
+        if result[..index]
+            .iter()
+            .any(|other| other.instance() == instance)
+        {
+            return Err(config::block_error(
+                &data,
+                index,
+                "id",
+                format!("duplicate block id '{}'", instance),
+            ));
+        }
+    }
+    Ok(result)
+}
+
+fn failure(e: &ConfigError) -> BlocksCollection {
+    let mut failure = Failure::new(format!("rstatus: {}", e));
+    failure.set_name("error".to_owned());
+    vec![Box::new(failure)]
+}
+
+fn failed(blocks: &BlocksCollection) -> bool {
+    blocks.len() == 1 && blocks[0].name() == "error"
 }
 
 fn init_blocks() -> BlocksCollection {
-    let home = std::env::var_os("HOME").expect("error getting home var");
-    let cfg_path = std::path::Path::new(&home)
-        .join(".config")
-        .join("rstatus")
-        .join("config.yaml");
+    let cfg_path = CONFIG_PATH.get().expect("config path is not set");
+    load(cfg_path).unwrap_or_else(|e| {
+        eprintln!("rstatus: {}: {}", cfg_path.display(), e);
+        failure(&e)
+    })
+}
 
-    init_from_config(&cfg_path)
+/// rebuild the blocks from the config, the current ones are kept if the
+/// config is broken
+pub fn reload() {
+    let cfg_path = CONFIG_PATH.get().expect("config path is not set");
+    let blocks = blocks();
+    let fresh = match load(cfg_path) {
+        Ok(fresh) => fresh,
+        Err(e) => {
+            eprintln!("rstatus: {}: {}", cfg_path.display(), e);
+            if !failed(&blocks.lock().unwrap()) {
+                return;
+            }
+            failure(&e)
+        }
+    };
+
+    let stale = {
+        let mut current = blocks.lock().unwrap();
+        let stale = std::mem::replace(&mut *current, fresh);
+        for block in current.iter_mut() {
+            update(block);
+        }
+        stale
+    };
+    // background listeners of the stale blocks stop when they are dropped,
+    // some of them wait for the blocks lock, so it must not be held here
+    drop(stale);
+    register_signals();
+    schedule();
 }
 
-pub fn display_all() {
+fn update_by_signal(sig: i32) {
+    let mut refresh = false;
+    for block in blocks().lock().unwrap().iter_mut() {
+        if sig == block.signal() as i32 + utility::SIGRTMIN {
+            update(block);
+            refresh = true;
+        }
+    }
+    if refresh {
+        scheduler::redraw();
+    }
+}
+
+pub fn register_signals() {
+    for block in blocks().lock().unwrap().iter() {
+        let signal = block.signal();
+        if signal != 0 {
+            utility::signal(utility::SIGRTMIN + signal as i32, update_by_signal);
+        }
+    }
+}
+
+pub fn status_line() -> String {
     let mut line = String::from("[");
     let mut first = true;
     let blocks = blocks();
@@ -110,11 +230,98 @@ pub fn display_all() {
         block.render(&mut line, prev_bg);
         prev_bg = block.bgcolor();
     }
-    line.push_str("],");
-    println!("{}", line);
+    line.push(']');
+    line
+}
+
+pub fn display_all() {
+    println!("{},", status_line());
+}
+
+/// hand the update periods of the blocks over to the scheduler
+pub fn schedule() {
+    let timers = blocks()
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|block| scheduler::Timer {
+            interval: block.interval(),
+            align: block.align(),
+        })
+        .collect();
+    scheduler::reset(timers);
+}
+
+/// update the block and keep its new value in the history, a deferred
+/// block has one only if its job is done already, e.g. when run inline
+pub fn update(block: &mut Box<dyn Block>) {
+    block.update();
+    if !block.deferred() || block.finish() {
+        block.sample();
+    }
 }
 
 pub fn update_by_index(index: usize) {
-    blocks().lock().unwrap()[index].update();
-    display_all();
+    // the index may be stale if the config was reloaded meanwhile
+    if let Some(block) = blocks().lock().unwrap().get_mut(index) {
+        update(block);
+        scheduler::redraw();
+    }
+}
+
+pub fn update_all(indexes: &[usize]) {
+    let blocks = blocks();
+    let mut blocks = blocks.lock().unwrap();
+    for index in indexes {
+        if let Some(block) = blocks.get_mut(*index) {
+            update(block);
+        }
+    }
+}
+
+fn find(blocks: &BlocksCollection, instance: &str, name: &str) -> Option<usize> {
+    blocks
+        .iter()
+        .position(|block| block.instance() == instance && block.name() == name)
+}
+
+/// unlike the index, the instance keeps pointing to the same block if the
+/// config was reloaded meanwhile
+pub fn update_by_instance(instance: &str, name: &str) {
+    let blocks = blocks();
+    let mut blocks = blocks.lock().unwrap();
+    if let Some(index) = find(&blocks, instance, name) {
+        update(&mut blocks[index]);
+        scheduler::redraw();
+    }
+}
+
+/// called by the workers once the job started by update is done
+pub fn finish_by_instance(instance: &str, name: &str) {
+    let blocks = blocks();
+    let mut blocks = blocks.lock().unwrap();
+    if let Some(index) = find(&blocks, instance, name) {
+        if blocks[index].finish() {
+            blocks[index].sample();
+        }
+        scheduler::redraw();
+    }
+}
+
+pub fn click(event: &ClickEvent) {
+    let (redraw, action) = {
+        let blocks = blocks();
+        let mut blocks = blocks.lock().unwrap();
+        let Some(index) = find(&blocks, &event.instance, &event.name) else {
+            return;
+        };
+        let block = &mut blocks[index];
+        (block.click(event), block.action(event))
+    };
+    if redraw {
+        scheduler::redraw();
+    }
+    if let Some(action) = action {
+        action.run(event.instance.clone(), event.name.clone());
+    }
 }
@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+use std::process::exit;
+
+const USAGE: &str = "usage: rstatus [options]
+
+options:
+  -c, --config <path>  config file, default is
+                       $XDG_CONFIG_HOME/rstatus/config.yaml or
+                       $HOME/.config/rstatus/config.yaml
+  -w, --watch          reload the config when the file changes, it is always
+                       reloaded on SIGHUP
+      --check          validate the config and exit
+      --once           update every block once, print a single status line and exit
+  -V, --version        print version and exit
+  -h, --help           print this help and exit";
+
+pub struct Options {
+    pub config: PathBuf,
+    pub check: bool,
+    pub once: bool,
+    pub watch: bool,
+}
+
+fn default_config() -> PathBuf {
+    let dir = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let home = std::env::var_os("HOME").expect("error getting home var");
+            PathBuf::from(home).join(".config")
+        }
+    };
+    dir.join("rstatus").join("config.yaml")
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("rstatus: {}\n{}", message, USAGE);
+    exit(2);
+}
+
+pub fn parse() -> Options {
+    let mut config = None;
+    let mut check = false;
+    let mut once = false;
+    let mut watch = false;
+
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        let Some(arg) = arg.to_str() else {
+            usage_error(&format!("invalid argument {:?}", arg));
+        };
+        if let Some(path) = arg.strip_prefix("--config=") {
+            config = Some(PathBuf::from(path));
+            continue;
+        }
+        match arg {
+            "-c" | "--config" => match args.next() {
+                Some(path) => config = Some(PathBuf::from(path)),
+                None => usage_error(&format!("{} requires a path", arg)),
+            },
+            "--check" => check = true,
+            "--once" => once = true,
+            "-w" | "--watch" => watch = true,
+            "-V" | "--version" => {
+                println!("rstatus {}", env!("CARGO_PKG_VERSION"));
+                exit(0);
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                exit(0);
+            }
+            _ => usage_error(&format!("unknown argument '{}'", arg)),
+        }
+    }
+
+    Options {
+        config: config.unwrap_or_else(default_config),
+        check,
+        once,
+        watch,
+    }
+}
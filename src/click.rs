@@ -0,0 +1,189 @@
+use super::blocks;
+use super::config::Shorthand;
+use serde::{de::Error, Deserialize, Deserializer};
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead},
+    process::{Command, Stdio},
+    thread,
+};
+
+/// click event as sent by i3bar/swaybar on stdin
+#[derive(Deserialize)]
+pub struct ClickEvent {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub instance: String,
+    #[serde(default)]
+    pub button: u32,
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+    #[serde(default)]
+    pub x: f64,
+    #[serde(default)]
+    pub y: f64,
+    #[serde(default)]
+    pub relative_x: f64,
+    #[serde(default)]
+    pub relative_y: f64,
+    #[serde(default)]
+    pub width: f64,
+    #[serde(default)]
+    pub height: f64,
+}
+
+const BUTTONS: [(&str, u32); 9] = [
+    ("left", 1),
+    ("middle", 2),
+    ("right", 3),
+    ("scroll_up", 4),
+    ("scroll_down", 5),
+    ("scroll_left", 6),
+    ("scroll_right", 7),
+    ("back", 8),
+    ("forward", 9),
+];
+
+const MODIFIERS: [(&str, &str); 9] = [
+    ("shift", "Shift"),
+    ("ctrl", "Control"),
+    ("control", "Control"),
+    ("alt", "Mod1"),
+    ("mod1", "Mod1"),
+    ("mod3", "Mod3"),
+    ("super", "Mod4"),
+    ("mod4", "Mod4"),
+    ("mod5", "Mod5"),
+];
+
+/// button with the modifiers held while it was clicked
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+pub struct Button {
+    button: u32,
+    modifiers: Vec<&'static str>,
+}
+
+impl Button {
+    fn parse(key: &str) -> Result<Self, String> {
+        let mut parts: Vec<&str> = key.split('+').map(str::trim).collect();
+        let name = parts.pop().unwrap_or_default().to_lowercase();
+        let button = BUTTONS
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map(|(_, button)| *button)
+            .ok_or_else(|| format!("unknown button '{}' in '{}'", name, key))?;
+        let mut modifiers = Vec::new();
+        for part in parts {
+            let modifier = MODIFIERS
+                .iter()
+                .find(|(known, _)| *known == part.to_lowercase())
+                .map(|(_, modifier)| *modifier)
+                .ok_or_else(|| format!("unknown modifier '{}' in '{}'", part, key))?;
+            modifiers.push(modifier);
+        }
+        modifiers.sort();
+        modifiers.dedup();
+        Ok(Button { button, modifiers })
+    }
+
+    fn from_event(event: &ClickEvent) -> Self {
+        // Mod2 (num lock) and Lock (caps lock) are latched rather than held,
+        // so they would make every binding miss while they are on
+        let mut modifiers: Vec<&'static str> = MODIFIERS
+            .iter()
+            .map(|(_, modifier)| *modifier)
+            .filter(|modifier| event.modifiers.iter().any(|held| held == modifier))
+            .collect();
+        modifiers.sort();
+        modifiers.dedup();
+        Button {
+            button: event.button,
+            modifiers,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ClickAction {
+    command: String,
+    refresh: bool,
+}
+
+impl ClickAction {
+    /// run the command in background, then update the block if asked to
+    pub fn run(self, instance: String, name: String) {
+        thread::spawn(move || {
+            // stdin carries the click events and stdout the status line,
+            // the command must not touch either of them
+            let _ = Command::new("sh")
+                .arg("-c")
+                .arg(&self.command)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .status();
+            if self.refresh {
+                blocks::update_by_instance(&instance, &name);
+            }
+        });
+    }
+}
+
+/// an action written as a map, a plain string is just its command
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ActionConfig {
+    command: String,
+    #[serde(default)]
+    refresh: bool,
+}
+
+pub type ClickActions = BTreeMap<Button, ClickAction>;
+
+pub fn find_action<'a>(actions: &'a ClickActions, event: &ClickEvent) -> Option<&'a ClickAction> {
+    actions.get(&Button::from_event(event))
+}
+
+pub fn parse_on_click<'de, D>(deserializer: D) -> Result<ClickActions, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let ms: BTreeMap<String, Shorthand<ActionConfig>> = BTreeMap::deserialize(deserializer)?;
+    let mut result = ClickActions::new();
+    for (key, action) in ms {
+        let button = Button::parse(&key).map_err(D::Error::custom)?;
+        let action = match action {
+            Shorthand::Short(command) => ClickAction {
+                command,
+                refresh: false,
+            },
+            Shorthand::Full(ActionConfig { command, refresh }) => ClickAction { command, refresh },
+        };
+        result.insert(button, action);
+    }
+    Ok(result)
+}
+
+fn parse(line: &str) -> Option<ClickEvent> {
+    // the events come as an endless json array: "[" first, then one object
+    // per line, every object but the first one prefixed with a comma
+    let text = line.trim().trim_start_matches(['[', ',']).trim();
+    if text.is_empty() {
+        return None;
+    }
+    // json is a subset of yaml, so no need for a separate json parser
+    serde_yaml::from_str(text).ok()
+}
+
+pub fn listen() {
+    thread::spawn(|| {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if let Some(event) = parse(&line) {
+                blocks::click(&event);
+            }
+        }
+    });
+}
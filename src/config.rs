@@ -0,0 +1,301 @@
+use super::base::{self, Base};
+use serde::de::{self, value::MapAccessDeserializer, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use serde_yaml::{Mapping, Value};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// config error pointing at the offending block and option
+pub struct ConfigError {
+    message: String,
+    block: Option<(usize, String)>,
+    location: Option<(usize, usize)>,
+}
+
+impl ConfigError {
+    pub fn new(message: String) -> Self {
+        ConfigError {
+            message,
+            block: None,
+            location: None,
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((line, column)) = self.location {
+            write!(f, "line {}, column {}: ", line, column)?;
+        }
+        if let Some((index, ref tag)) = self.block {
+            if tag.is_empty() {
+                write!(f, "block {}: ", index)?;
+            } else {
+                write!(f, "block {} (!{}): ", index, tag)?;
+            }
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// option written either as a single string or as a map, an untagged enum
+/// would report a typo in the map as a mismatch of every variant
+pub enum Shorthand<T> {
+    Short(String),
+    Full(T),
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Shorthand<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ShorthandVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for ShorthandVisitor<T> {
+            type Value = Shorthand<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a string or a map of options")
+            }
+
+            fn visit_str<E: de::Error>(self, text: &str) -> Result<Self::Value, E> {
+                Ok(Shorthand::Short(text.to_owned()))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                T::deserialize(MapAccessDeserializer::new(map)).map(Shorthand::Full)
+            }
+        }
+
+        deserializer.deserialize_any(ShorthandVisitor(PhantomData))
+    }
+}
+
+/// option names of a block type, None if the type is unknown
+pub type FieldsLookup = fn(&str) -> Option<&'static [&'static str]>;
+
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn suggest<'a>(name: &str, known: impl Iterator<Item = &'a str>) -> String {
+    let limit = (name.chars().count() / 3).max(1);
+    known
+        .map(|candidate| (distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min()
+        .map_or_else(String::new, |(_, candidate)| {
+            format!(", did you mean '{}'?", candidate)
+        })
+}
+
+/// lines where the top level sequence items start, 0-based
+fn block_lines(text: &str) -> Vec<usize> {
+    let items: Vec<(usize, usize)> = text
+        .lines()
+        .enumerate()
+        .filter_map(|(number, line)| {
+            let trimmed = line.trim_start();
+            let item = trimmed == "-" || trimmed.starts_with("- ");
+            item.then(|| (number, line.len() - trimmed.len()))
+        })
+        .collect();
+    let indent = items.iter().map(|(_, indent)| *indent).min().unwrap_or(0);
+    items
+        .into_iter()
+        .filter(|(_, item_indent)| *item_indent == indent)
+        .map(|(number, _)| number)
+        .collect()
+}
+
+fn block_at(text: &str, line: usize) -> Option<usize> {
+    block_lines(text).iter().rposition(|start| *start <= line)
+}
+
+/// best effort search of an option inside a block written in block style
+fn locate(text: &str, index: usize, key: &str) -> Option<(usize, usize)> {
+    let starts = block_lines(text);
+    let first = *starts.get(index)?;
+    let last = starts.get(index + 1).copied().unwrap_or(usize::MAX);
+    text.lines()
+        .enumerate()
+        .skip(first)
+        .take_while(|(number, _)| *number < last)
+        .find_map(|(number, line)| {
+            let trimmed = line.trim_start_matches([' ', '-']);
+            let column = line.len() - trimmed.len() + 1;
+            [
+                format!("{}:", key),
+                format!("'{}':", key),
+                format!("\"{}\":", key),
+            ]
+            .iter()
+            .any(|pattern| trimmed.starts_with(pattern.as_str()))
+            .then_some((number + 1, column))
+        })
+}
+
+fn tag_of(item: &Value) -> Option<String> {
+    match item {
+        Value::Tagged(tagged) => Some(tagged.tag.to_string().trim_start_matches('!').to_owned()),
+        _ => None,
+    }
+}
+
+fn options(item: &Value) -> Option<&Mapping> {
+    match item {
+        Value::Tagged(tagged) => tagged.value.as_mapping(),
+        _ => None,
+    }
+}
+
+/// check block types and option names, serde ignores unknown options of
+/// flattened structs, so they are checked here before the actual parsing
+pub fn validate(text: &str, tags: &[&str], fields: FieldsLookup) -> Result<(), ConfigError> {
+    let document: Value = serde_yaml::from_str(text).map_err(|e| from_yaml(text, &e))?;
+    let Value::Sequence(items) = document else {
+        return Err(ConfigError::new(
+            "the config must be a sequence of blocks".to_owned(),
+        ));
+    };
+
+    for (index, item) in items.iter().enumerate() {
+        let error = |message: String, key: Option<&str>| ConfigError {
+            message,
+            block: Some((index, tag_of(item).unwrap_or_default())),
+            location: key
+                .and_then(|key| locate(text, index, key))
+                .or_else(|| block_lines(text).get(index).map(|line| (line + 1, 1))),
+        };
+
+        let Some(tag) = tag_of(item) else {
+            return Err(error(
+                "expected a block type tag like '!time'".to_owned(),
+                None,
+            ));
+        };
+        let Some(own) = fields(&tag) else {
+            return Err(error(
+                format!(
+                    "unknown block type '!{}'{}",
+                    tag,
+                    suggest(&tag, tags.iter().copied())
+                ),
+                None,
+            ));
+        };
+        let Some(mapping) = options(item) else {
+            if item.as_null().is_some() {
+                continue;
+            }
+            return Err(error("expected a map of options".to_owned(), None));
+        };
+
+        for key in mapping.keys() {
+            let Some(key) = key.as_str() else {
+                return Err(error("option names must be strings".to_owned(), None));
+            };
+            if own.contains(&key) || base::FIELDS.contains(&key) {
+                continue;
+            }
+            let known = own.iter().chain(base::FIELDS.iter()).copied();
+            return Err(error(
+                format!("unknown option '{}'{}", key, suggest(key, known)),
+                Some(key),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// a flattened Base reports its errors at the start of the block, so find
+/// the culprit by parsing its options one by one
+fn base_culprit(item: &Value) -> Option<String> {
+    options(item)?
+        .iter()
+        .filter_map(|(key, value)| Some((key.as_str()?, value)))
+        .filter(|(key, _)| base::FIELDS.contains(key) || base::COMMAND_FIELDS.contains(key))
+        .find(|(key, value)| {
+            let mut single = Mapping::new();
+            single.insert(Value::from(*key), (*value).clone());
+            serde_yaml::from_value::<Base>(Value::Mapping(single)).is_err()
+        })
+        .map(|(key, _)| key.to_owned())
+}
+
+/// turn a serde error into a config error, the text is the parsed config
+pub fn from_yaml(text: &str, e: &serde_yaml::Error) -> ConfigError {
+    let full = e.to_string();
+    let message = match full.rsplit_once(" at line ") {
+        Some((message, _)) => message,
+        None => &full,
+    };
+
+    // the message is prefixed by the path of the value, like ".[2].format: "
+    let mut index = None;
+    let mut key = None;
+    let mut message = message.to_owned();
+    if let Some(rest) = message.strip_prefix(".[") {
+        if let Some((number, rest)) = rest.split_once(']') {
+            if let Some((path, reason)) = rest.split_once(": ") {
+                index = number.parse::<usize>().ok();
+                key = path
+                    .strip_prefix('.')
+                    .map(|path| path.split(['.', '[']).next().unwrap_or(path).to_owned());
+                message = reason.to_owned();
+            }
+        }
+    }
+    let location = e
+        .location()
+        .map(|location| (location.line(), location.column()));
+    let index = index.or_else(|| location.and_then(|(line, _)| block_at(text, line - 1)));
+
+    let Some(index) = index else {
+        return ConfigError {
+            message,
+            block: None,
+            location,
+        };
+    };
+
+    let items: Vec<Value> = serde_yaml::from_str(text).unwrap_or_default();
+    let tag = items.get(index).and_then(tag_of).unwrap_or_default();
+    if key.is_none() {
+        key = items.get(index).and_then(base_culprit);
+    }
+    match key {
+        Some(key) => ConfigError {
+            message: format!("option '{}': {}", key, message),
+            location: locate(text, index, &key).or(location),
+            block: Some((index, tag)),
+        },
+        None => ConfigError {
+            message,
+            location,
+            block: Some((index, tag)),
+        },
+    }
+}
+
+/// error for a block which is wrong as a whole
+pub fn block_error(text: &str, index: usize, key: &str, message: String) -> ConfigError {
+    let items: Vec<Value> = serde_yaml::from_str(text).unwrap_or_default();
+    ConfigError {
+        message,
+        block: Some((index, items.get(index).and_then(tag_of).unwrap_or_default())),
+        location: locate(text, index, key),
+    }
+}
@@ -3,6 +3,9 @@ use super::block;
 use super::utility;
 use serde::Deserialize;
 
+/// options of the block, Base ones excluded
+pub const FIELDS: &[&str] = &[];
+
 #[derive(Deserialize)]
 pub struct Block {
     #[serde(flatten)]
@@ -38,6 +41,7 @@ impl block::Block for Block {
                 }
             }
 
+            #[allow(clippy::manual_checked_ops)]
             unsafe {
                 self.base.value = if let Some(ref prev) = PREV_STAT {
                     let delta_total = stat.total.saturating_sub(prev.total);
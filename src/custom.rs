@@ -1,52 +1,304 @@
-use super::base::{Base, Value};
+use super::base::{default_false, default_str_none, Attributes, Base, Value};
 use super::block;
+use super::click::ClickEvent;
 use super::utility::read_color;
+use super::worker::{self, Environment, Output, RunError, Stream, Worker};
 use serde::Deserialize;
+use std::time::Duration;
 
+/// options of the block, Base ones excluded
+pub const FIELDS: &[&str] = &["command", "persistent", "format", "timeout", "stale_color"];
+
+/// protocol of the command output
+#[derive(PartialEq)]
+enum Protocol {
+    /// value on the first line, optional color on the second one
+    Text,
+    /// a json object, one per line in persistent mode
+    Json,
+}
+
+/// object printed by the command in json format
 #[derive(Deserialize)]
-pub struct Block {
+struct JsonOutput {
+    full_text: Option<String>,
+    short_text: Option<String>,
+    color: Option<String>,
+    background: Option<String>,
+    #[serde(default)]
+    urgent: bool,
+    prefix: Option<String>,
+    suffix: Option<String>,
+    /// number checked against thresholds, it is the value if there is no
+    /// full_text
+    value: Option<f64>,
+}
+
+/// the options as written, format is either the protocol or a template
+/// laying out the value, which Base cannot see since the key is taken here
+#[derive(Deserialize)]
+struct Config {
     #[serde(flatten)]
     base: Base,
     command: String,
+    #[serde(default = "default_false")]
+    persistent: bool,
+    #[serde(default = "default_str_none")]
+    format: Option<String>,
 }
 
-impl block::Block for Block {
-    impl_Block!();
+#[derive(Deserialize)]
+#[serde(from = "Config")]
+pub struct Block {
+    base: Base,
+    command: String,
+    /// start the command once and take every line it prints as the value
+    persistent: bool,
+    protocol: Protocol,
+    worker: Worker<Result<Output, RunError>>,
+    /// the command was started at least once
+    ran: bool,
+    stream: Option<Stream>,
+}
 
-    fn update(&mut self) {
-        use std::process::Command;
-        use std::str;
-        let output = match Command::new("sh").arg("-c").arg(&self.command).output() {
-            Ok(o) => o,
-            Err(_) => {
-                self.base.value = Value::Invalid;
-                return;
+impl From<Config> for Block {
+    fn from(config: Config) -> Self {
+        let mut base = config.base;
+        let protocol = match config.format.as_deref() {
+            Some("json") => Protocol::Json,
+            None | Some("text") => Protocol::Text,
+            Some(template) => {
+                base.set_format(template.to_owned());
+                Protocol::Text
             }
         };
-        let strval = match str::from_utf8(&output.stdout) {
-            Ok(s) => s.to_string(),
-            Err(_) => {
-                self.base.value = Value::Invalid;
-                return;
+        // nothing to show until the command printed something, invalid
+        // would flash on every start and reload
+        base.value = Value::new("");
+        Block {
+            base,
+            command: config.command,
+            persistent: config.persistent,
+            protocol,
+            worker: Worker::default(),
+            ran: false,
+            stream: None,
+        }
+    }
+}
+
+/// exit code of the command asking for the block to be urgent, as in i3blocks
+const EXIT_URGENT: i32 = 33;
+
+/// how long the first update waits for the command while it holds the
+/// blocks, so that a quick one is in the very first status line
+const FIRST_RUN_TIMEOUT: Duration = Duration::from_millis(200);
+
+const CLICK_VARIABLES: [&str; 8] = [
+    "BLOCK_BUTTON",
+    "BLOCK_MODIFIERS",
+    "BLOCK_X",
+    "BLOCK_Y",
+    "BLOCK_RELATIVE_X",
+    "BLOCK_RELATIVE_Y",
+    "BLOCK_WIDTH",
+    "BLOCK_HEIGHT",
+];
+
+fn is_color(text: &str) -> bool {
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn parse_value(line: &str) -> Value {
+    if let Ok(value) = line.parse::<u32>() {
+        Value::new(value)
+    } else if let Some(value) = line.parse::<f64>().ok().filter(|value| value.is_finite()) {
+        Value::new(value)
+    } else if !line.is_empty() {
+        Value::new(line)
+    } else {
+        Value::Invalid
+    }
+}
+
+impl Block {
+    fn apply_json(&mut self, text: &str) {
+        self.base.attributes = Attributes::default();
+        // json is a subset of yaml
+        let Ok(output) = serde_yaml::from_str::<JsonOutput>(text) else {
+            self.base.value = Value::Invalid;
+            return;
+        };
+
+        let number = output.value.filter(|value| value.is_finite());
+        let level = number.map(|value| value.round().clamp(0.0, u32::MAX as f64) as u32);
+        self.base.value = match (output.full_text, number) {
+            (Some(text), _) if !text.is_empty() => Value::new(text),
+            (_, Some(number)) if number.fract() != 0.0 => Value::new(number),
+            (_, Some(_)) => Value::new(level),
+            _ => Value::Invalid,
+        };
+        self.base.attributes = Attributes {
+            color: output.color.map(|color| read_color(&color, 0xffffff)),
+            bgcolor: output.background.map(|color| read_color(&color, 0)),
+            short_text: output.short_text,
+            urgent: output.urgent,
+            prefix: output.prefix,
+            suffix: output.suffix,
+            level,
+        };
+    }
+
+    /// value, then either color or short text, then color, the latter
+    /// being the i3blocks protocol
+    fn apply_text(&mut self, text: &str) {
+        self.base.attributes = Attributes::default();
+        let lines: Vec<&str> = text
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .collect();
+        self.base.value = parse_value(lines.first().copied().unwrap_or_default());
+        let color = match lines.get(1) {
+            Some(line) if lines.len() == 2 && is_color(line) => Some(*line),
+            Some(line) => {
+                self.base.attributes.short_text = Some(line.to_string());
+                lines.get(2).copied()
             }
+            None => None,
         };
+        // lasts until the next update, thresholds still take precedence
+        self.base.attributes.color = color
+            .filter(|color| !color.is_empty())
+            .map(|color| read_color(color, 0xffffff));
+    }
 
-        let data: Vec<&str> = strval.split('\n').collect();
-        self.base.value = match data.len() {
-            0 => Value::Invalid,
-            1 => {
-                if let Ok(value) = data[0].parse::<u32>() {
-                    Value::new(value)
-                } else if !data[0].is_empty() {
-                    Value::new(data[0])
-                } else {
-                    Value::Invalid
+    fn apply(&mut self, output: Output) {
+        let Ok(text) = std::str::from_utf8(&output.stdout) else {
+            self.base.attributes = Attributes::default();
+            self.base.value = Value::Invalid;
+            return;
+        };
+        match self.protocol {
+            Protocol::Text => self.apply_text(text),
+            Protocol::Json => self.apply_json(text),
+        }
+        match output.status.code() {
+            Some(0) => {}
+            Some(EXIT_URGENT) => self.base.attributes.urgent = true,
+            _ => self.base.value = Value::Invalid,
+        }
+    }
+
+    /// variables set by i3blocks, the click ones are empty unless the
+    /// command runs because of a click
+    fn environment(&self, event: Option<&ClickEvent>) -> Environment {
+        let click = match event {
+            Some(event) => vec![
+                event.button.to_string(),
+                event.modifiers.join(","),
+                event.x.to_string(),
+                event.y.to_string(),
+                event.relative_x.to_string(),
+                event.relative_y.to_string(),
+                event.width.to_string(),
+                event.height.to_string(),
+            ],
+            None => vec![String::new(); CLICK_VARIABLES.len()],
+        };
+        [
+            ("BLOCK_NAME", self.base.name().to_owned()),
+            ("BLOCK_INSTANCE", self.base.instance()),
+        ]
+        .into_iter()
+        .chain(CLICK_VARIABLES.iter().copied().zip(click))
+        .map(|(name, value)| (name.to_owned(), value))
+        .collect()
+    }
+
+    /// run the command in background, returns false if it is running already
+    fn run(&mut self, event: Option<&ClickEvent>) -> bool {
+        let command = self.command.clone();
+        let env = self.environment(event);
+        let timeout = self.base.timeout();
+        let (instance, name) = (self.base.instance(), self.base.name().to_owned());
+        self.worker
+            .start(instance, name, move || worker::run(&command, &env, timeout))
+    }
+}
+
+impl block::Block for Block {
+    impl_Block!();
+
+    fn update(&mut self) {
+        if self.persistent {
+            if self.stream.is_none() {
+                let env = self.environment(None);
+                let (instance, name) = (self.base.instance(), self.base.name().to_owned());
+                self.stream = Some(Stream::start(instance, name, &self.command, env));
+            }
+            return;
+        }
+
+        if !self.run(None) {
+            // the previous command is still running, it is late by now
+            let late = self
+                .worker
+                .running_for()
+                .is_some_and(|running| running >= self.base.interval());
+            self.base.set_stale(late);
+        } else if !self.ran {
+            self.ran = true;
+            self.worker.wait(FIRST_RUN_TIMEOUT);
+        }
+    }
+
+    fn deferred(&self) -> bool {
+        true
+    }
+
+    fn finish(&mut self) -> bool {
+        if let Some(ref stream) = self.stream {
+            let Some(line) = stream.take() else {
+                return false;
+            };
+            let line = line.trim_end_matches('\r');
+            match self.protocol {
+                Protocol::Text => {
+                    self.base.attributes = Attributes::default();
+                    self.base.value = parse_value(line);
                 }
+                Protocol::Json => self.apply_json(line),
             }
-            _ => {
-                self.base.set_color(read_color(data[1], 0xffffff));
-                Value::new(data[0])
+            return true;
+        }
+
+        match self.worker.take() {
+            Some(Ok(output)) => {
+                self.base.set_stale(false);
+                self.apply(output);
+            }
+            // the last good value is kept
+            Some(Err(RunError::TimedOut)) => {
+                self.base.set_stale(true);
+                return false;
+            }
+            Some(Err(RunError::Failed)) => {
+                self.base.set_stale(false);
+                self.base.attributes = Attributes::default();
+                self.base.value = Value::Invalid;
             }
+            None => return false,
+        }
+        true
+    }
+
+    /// the command learns about the click from BLOCK_BUTTON, unless the
+    /// button has an on_click action of its own
+    fn click(&mut self, event: &ClickEvent) -> bool {
+        if !self.persistent && self.base.action(event).is_none() {
+            self.run(Some(event));
         }
+        false
     }
 }
@@ -0,0 +1,95 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use zbus::{
+    blocking::{fdo::DBusProxy, Connection, MessageIterator},
+    MatchRule, Message,
+};
+
+/// connection of the running monitor, closed to stop it
+type SharedConnection = Arc<Mutex<Option<Connection>>>;
+
+/// what the monitor thread gets to tell whether it should keep going
+#[derive(Clone)]
+pub struct Handle {
+    alive: Arc<AtomicBool>,
+    connection: SharedConnection,
+}
+
+impl Handle {
+    pub fn alive(&self) -> bool {
+        self.alive.load(Ordering::Acquire)
+    }
+
+    /// make conn the one closed once the listener is dropped and ask for the
+    /// signals, false if it was dropped already
+    pub fn subscribe<'m>(
+        &self,
+        conn: &Connection,
+        rules: impl IntoIterator<Item = MatchRule<'m>>,
+    ) -> zbus::Result<bool> {
+        {
+            let mut shared = self.connection.lock().unwrap();
+            if !self.alive() {
+                return Ok(false);
+            }
+            *shared = Some(conn.clone());
+        }
+        let dbus = DBusProxy::new(conn)?;
+        for rule in rules {
+            dbus.add_match_rule(rule)?;
+        }
+        Ok(true)
+    }
+
+    /// the messages of conn until the listener is dropped
+    pub fn messages<'a>(&'a self, conn: &Connection) -> impl Iterator<Item = Message> + 'a {
+        MessageIterator::from(conn)
+            .take_while(|_| self.alive())
+            .filter_map(Result::ok)
+    }
+}
+
+/// runs a monitor in a background thread, connecting again after a delay
+/// whenever it returns, until the listener is dropped
+pub struct Listener {
+    handle: Handle,
+}
+
+impl Listener {
+    pub fn spawn<F>(reconnect_delay: Duration, mut monitor: F) -> Self
+    where
+        F: FnMut(&Handle) + Send + 'static,
+    {
+        let handle = Handle {
+            alive: Arc::new(AtomicBool::new(true)),
+            connection: Arc::new(Mutex::new(None)),
+        };
+        let thread_handle = handle.clone();
+        thread::spawn(move || {
+            while thread_handle.alive() {
+                monitor(&thread_handle);
+                if thread_handle.alive() {
+                    thread::sleep(reconnect_delay);
+                }
+            }
+        });
+        Listener { handle }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.handle.alive.store(false, Ordering::Release);
+        // closing the connection wakes up the monitor waiting for messages
+        if let Some(connection) = self.handle.connection.lock().unwrap().take() {
+            let _ = connection.close();
+        }
+    }
+}
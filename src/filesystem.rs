@@ -1,7 +1,11 @@
 use super::base::{Base, Value};
 use super::block;
+use super::template::Field;
 use serde::Deserialize;
 
+/// options of the block, Base ones excluded
+pub const FIELDS: &[&str] = &["path"];
+
 #[derive(Deserialize)]
 pub struct Block {
     #[serde(flatten)]
@@ -14,6 +18,7 @@ impl block::Block for Block {
     fn update(&mut self) {
         use std::ffi::CString;
         use std::mem;
+        #[allow(clippy::manual_checked_ops, clippy::manual_div_ceil)]
         unsafe {
             let path = match CString::new(self.path.clone()) {
                 Ok(p) => p,
@@ -34,6 +39,14 @@ impl block::Block for Block {
             } else {
                 Value::new(((used * 100 + total - 1) / total) as u32)
             };
+            // the blocks reserved for root count neither as used nor as free
+            let size = usage.f_frsize as u64;
+            self.base.placeholders = vec![
+                ("used", Field::Bytes(used as u64 * size)),
+                ("total", Field::Bytes(total as u64 * size)),
+                ("free", Field::Bytes(usage.f_bavail as u64 * size)),
+                ("path", Field::from(self.path.as_str())),
+            ];
         }
     }
 }
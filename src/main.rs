@@ -16,66 +16,77 @@ mod alsa_dev;
 mod pipewire_dev;
 
 mod block_builder;
+mod cli;
+mod click;
+mod config;
+mod dbus_listener;
 mod mpris_dev;
+mod scheduler;
 mod sound_service;
+mod template;
+mod upower_dev;
 mod utility;
+mod watcher;
+mod worker;
 
 all_blocks! {mod_blocks}
 
-fn update_by_signal(sig: i32) {
-    let mut refresh = false;
-    for block in blocks::blocks().lock().unwrap().iter_mut() {
-        if sig == block.signal() as i32 + utility::SIGRTMIN {
-            block.update();
-            refresh = true;
-        }
-    }
-    if refresh {
-        blocks::display_all();
-    }
+fn reload_by_signal(_sig: i32) {
+    blocks::reload();
 }
 
 fn main() {
-    let blocks = blocks::blocks();
-    let gcd = blocks
-        .lock()
-        .unwrap()
-        .iter()
-        .map(|block| block.interval())
-        .fold(0, utility::gcd);
+    let options = cli::parse();
+    if options.check {
+        if let Err(e) = blocks::load(&options.config) {
+            eprintln!("{}: {}", options.config.display(), e);
+            std::process::exit(1);
+        }
+        println!("{}: ok", options.config.display());
+        return;
+    }
 
-    if gcd != 0 {
-        let mut signals = vec![];
+    blocks::set_config(options.config.clone());
+    let blocks = blocks::blocks();
+    if options.once {
+        worker::run_inline();
         for block in blocks.lock().unwrap().iter_mut() {
-            block.update();
-            let signal = block.signal();
-            if signal != 0 {
-                utility::signal(signal as i32, update_by_signal);
-                signals.push(signal as i32);
-            }
+            blocks::update(block);
         }
+        println!("{}", blocks::status_line());
+        return;
+    }
 
-        println!("{{\"version\": 1, \"click_events\": false}}\n[");
-        if !signals.is_empty() {
-            std::thread::spawn(|| {
-                let day = std::time::Duration::from_secs(86400);
-                loop {
-                    std::thread::sleep(day);
-                }
-            });
-        }
-        utility::mask(signals);
-        let mut count = gcd as u64;
+    // signal handlers take the blocks lock, so only a thread which never
+    // holds it may run them. Threads spawned from now on inherit the mask
+    let handled: Vec<i32> = (utility::SIGRTMIN..=utility::SIGRTMAX)
+        .chain([libc::SIGHUP])
+        .collect();
+    utility::mask(&handled);
+    std::thread::spawn(move || {
+        utility::unmask(&handled);
+        let day = std::time::Duration::from_secs(86400);
         loop {
-            std::thread::sleep(std::time::Duration::from_secs(gcd as u64));
-            for block in blocks.lock().unwrap().iter_mut() {
-                let interval = block.interval();
-                if interval != 0 && count.is_multiple_of(interval as u64) {
-                    block.update();
-                }
-            }
-            blocks::display_all();
-            count += gcd as u64
+            std::thread::sleep(day);
         }
+    });
+    utility::signal(libc::SIGHUP, reload_by_signal);
+    blocks::register_signals();
+
+    for block in blocks.lock().unwrap().iter_mut() {
+        blocks::update(block);
+    }
+
+    println!("{{\"version\": 1, \"click_events\": true}}\n[");
+    click::listen();
+    if options.watch {
+        watcher::watch(&options.config, blocks::reload);
+    }
+    blocks::schedule();
+    scheduler::watch_clock();
+    loop {
+        let due = scheduler::wait();
+        blocks::update_all(&due);
+        blocks::display_all();
     }
 }
@@ -1,30 +1,50 @@
 use super::base::{Base, Value};
 use super::block;
+use super::template::Field;
 use serde::Deserialize;
 
+/// options of the block, Base ones excluded
+pub const FIELDS: &[&str] = &[];
+
 #[derive(Deserialize)]
 pub struct Block {
     #[serde(flatten)]
     base: Base,
 }
 
-fn get_mem_usage() -> Result<u32, ()> {
+/// total and available memory in bytes
+fn get_mem_info() -> Result<(u64, u64), ()> {
     let text = std::fs::read_to_string("/proc/meminfo").map_err(|_| ())?;
-    let find = |key: &str| -> Option<f64> {
+    let find = |key: &str| -> Option<u64> {
         let pos = text.find(key)?;
-        text[pos + key.len()..].split_whitespace().next()?.parse().ok()
+        let kib: u64 = text[pos + key.len()..]
+            .split_whitespace()
+            .next()?
+            .parse()
+            .ok()?;
+        Some(kib * 1024)
     };
     let total = find("MemTotal:").ok_or(())?;
     let available = find("MemAvailable:").ok_or(())?;
-    if total == 0.0 {
+    if total == 0 {
         return Err(());
     }
-    Ok(((1.0 - available / total) * 100.0) as u32)
+    Ok((total, available.min(total)))
 }
 
 impl block::Block for Block {
     impl_Block!();
     fn update(&mut self) {
-        self.base.value = Value::new(get_mem_usage());
+        let Ok((total, available)) = get_mem_info() else {
+            self.base.value = Value::Invalid;
+            return;
+        };
+        let used = total - available;
+        self.base.value = Value::new(((1.0 - available as f64 / total as f64) * 100.0) as u32);
+        self.base.placeholders = vec![
+            ("used", Field::Bytes(used)),
+            ("total", Field::Bytes(total)),
+            ("free", Field::Bytes(available)),
+        ];
     }
 }
@@ -1,9 +1,13 @@
 use super::base::{default_str_none, default_zero, Base, Status, Value};
 use super::block;
 use super::mpris_dev::{MprisService, PlaybackStatus, Snapshot, Track};
+use super::template::{self, truncate, Field, Resolved};
 use serde::Deserialize;
 use std::collections::BTreeMap;
 
+/// options of the block, Base ones excluded
+pub const FIELDS: &[&str] = &["statuses", "players", "format", "max_length"];
+
 #[derive(Deserialize)]
 pub struct Block {
     #[serde(flatten)]
@@ -32,38 +36,6 @@ fn default_service() -> Option<MprisService> {
     None
 }
 
-enum Resolved {
-    Value(String),
-    Unresolved,
-    Unknown,
-}
-
-fn truncate(text: String, budget: usize) -> String {
-    if text.chars().count() <= budget {
-        return text;
-    }
-    if budget == 0 {
-        return String::new();
-    }
-    let mut result: String = text.chars().take(budget).collect();
-    result.push('…');
-    result
-}
-
-fn take(text: &str, budget: &mut usize) -> String {
-    if *budget == usize::MAX {
-        return text.to_owned();
-    }
-    let count = text.chars().count();
-    if count <= *budget {
-        *budget -= count;
-        return text.to_owned();
-    }
-    let result = truncate(text.to_owned(), *budget);
-    *budget = 0;
-    result
-}
-
 fn duration(micros: i64) -> String {
     let total = (micros / 1_000_000).max(0);
     let (hours, minutes, seconds) = (total / 3600, (total / 60) % 60, total % 60);
@@ -88,79 +60,26 @@ fn position(snapshot: &Snapshot) -> Option<i64> {
     })
 }
 
-fn resolve(name: &str, track: &Track, position: Option<i64>, budget: &mut usize) -> Resolved {
+fn resolve(name: &str, track: &Track, position: Option<i64>) -> Resolved {
     let text = match name {
         "artist" => &track.artist,
         "title" => &track.title,
         "album" => &track.album,
         "player" => &track.player,
+        // the times are never shortened, so the clock survives a long title
         "position" => {
             return position.map_or(Resolved::Unresolved, |value| {
-                Resolved::Value(duration(value))
+                Resolved::Value(Field::Raw(duration(value)))
             })
         }
         "length" => {
             return track.length.map_or(Resolved::Unresolved, |value| {
-                Resolved::Value(duration(value))
+                Resolved::Value(Field::Raw(duration(value)))
             })
         }
         _ => return Resolved::Unknown,
     };
-    if text.is_empty() {
-        return Resolved::Unresolved;
-    }
-    Resolved::Value(take(text, budget))
-}
-
-fn render(
-    format: &str,
-    track: &Track,
-    position: Option<i64>,
-    budget: &mut usize,
-    grouped: bool,
-) -> Option<String> {
-    let mut out = String::new();
-    let mut rest = format;
-    while !rest.is_empty() {
-        let Some(at) = rest.find(['{', '[']) else {
-            out.push_str(rest);
-            break;
-        };
-        out.push_str(&rest[..at]);
-        rest = &rest[at..];
-
-        if let Some(tail) = rest.strip_prefix('{') {
-            let Some(end) = tail.find('}') else {
-                out.push_str(rest);
-                break;
-            };
-            match resolve(&tail[..end], track, position, budget) {
-                Resolved::Value(value) => out.push_str(&value),
-                Resolved::Unresolved if grouped => return None,
-                Resolved::Unresolved => {}
-                Resolved::Unknown => out.push_str(&rest[..end + 2]),
-            }
-            rest = &tail[end + 1..];
-        } else if grouped {
-            out.push('[');
-            rest = &rest[1..];
-        } else {
-            let Some(end) = rest.find(']') else {
-                out.push('[');
-                rest = &rest[1..];
-                continue;
-            };
-            let mut local = *budget;
-            if let Some(group) = render(&rest[1..end], track, position, &mut local, true) {
-                out.push('[');
-                out.push_str(&group);
-                out.push(']');
-                *budget = local;
-            }
-            rest = &rest[end + 1..];
-        }
-    }
-    Some(out)
+    Resolved::Value(Field::Text(text.clone()))
 }
 
 impl Block {
@@ -183,9 +102,17 @@ impl Block {
         };
 
         let mut budget = budget;
-        render(format, track, position(snapshot), &mut budget, false)
-            .map(|text| text.trim().to_owned())
-            .unwrap_or_default()
+        let position = position(snapshot);
+        let resolve = |name: &str| resolve(name, track, position);
+        template::render(
+            format,
+            &resolve,
+            &mut budget,
+            self.base.markup(),
+            self.base.numbers(),
+        )
+        .trim()
+        .to_owned()
     }
 }
 
@@ -203,13 +130,13 @@ impl block::Block for Block {
         let snapshot = self.service.as_ref().and_then(|service| service.snapshot());
         self.base.value = match snapshot {
             Some(snapshot) => {
-                if let Some(decoration) = self.statuses.get(snapshot.track.status.name()) {
-                    self.base.set_prefix(&decoration.prefix);
-                    self.base.set_suffix(&decoration.suffix);
-                }
+                self.base
+                    .set_status(self.statuses.get(snapshot.track.status.name()));
                 let text = self.text(&snapshot);
                 if text.is_empty() {
                     Value::Invalid
+                } else if self.base.markup() && self.format.is_some() {
+                    Value::Markup(text)
                 } else {
                     Value::new(text)
                 }
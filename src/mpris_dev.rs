@@ -1,16 +1,17 @@
 use super::blocks::update_by_index;
+use super::dbus_listener::{Handle, Listener};
+use super::worker;
 
 use std::{
     collections::HashMap,
-    sync::{Arc, Mutex},
-    thread,
-    time::Instant,
+    sync::{mpsc, Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use zbus::{
     blocking::{
         fdo::{DBusProxy, PropertiesProxy},
-        Connection, MessageIterator,
+        Connection,
     },
     message::Type as MessageType,
     names::{BusName, InterfaceName},
@@ -18,7 +19,9 @@ use zbus::{
     MatchRule,
 };
 
-const RECONNECT_DELAY_SECS: u64 = 1;
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+/// how long the first update of a single status line waits for the players
+const FIRST_FETCH_TIMEOUT: Duration = Duration::from_secs(1);
 const BUS_NAMESPACE: &str = "org.mpris.MediaPlayer2";
 const BUS_PREFIX: &str = "org.mpris.MediaPlayer2.";
 const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
@@ -205,16 +208,23 @@ struct Monitor {
     priority: Vec<String>,
     players: HashMap<String, PlayerState>,
     seq: u64,
+    handle: Handle,
 }
 
 impl Monitor {
-    fn new(block_index: usize, cache: Arc<Mutex<MprisCache>>, priority: Vec<String>) -> Self {
+    fn new(
+        block_index: usize,
+        cache: Arc<Mutex<MprisCache>>,
+        priority: Vec<String>,
+        handle: Handle,
+    ) -> Self {
         Monitor {
             block_index,
             cache,
             priority,
             players: HashMap::new(),
             seq: 0,
+            handle,
         }
     }
 
@@ -320,37 +330,42 @@ impl Monitor {
         })
     }
 
-    fn refresh(&self) {
+    /// put the track of the selected player in the cache, true if it changed
+    fn store(&self) -> bool {
         let winner = self.select().filter(|player| player.track.known());
         let track = winner.map(|player| player.track.clone());
         let sampled = winner.map_or_else(Instant::now, |player| player.sampled);
 
-        {
-            let mut cache = self.cache.lock().unwrap();
-            if cache.track == track {
-                cache.sampled = sampled;
-                return;
-            }
-            cache.track = track;
-            cache.sampled = sampled;
+        let mut cache = self.cache.lock().unwrap();
+        cache.sampled = sampled;
+        if cache.track == track {
+            return false;
         }
-        update_by_index(self.block_index);
+        cache.track = track;
+        true
     }
 
-    fn run(&mut self) -> zbus::Result<()> {
-        let conn = Connection::session()?;
-        let dbus = DBusProxy::new(&conn)?;
+    fn refresh(&self) {
+        if self.store() {
+            self.update();
+        }
+    }
+
+    fn update(&self) {
+        if self.handle.alive() {
+            update_by_index(self.block_index);
+        }
+    }
 
-        dbus.add_match_rule(
+    fn run(&mut self, first: &mut Option<mpsc::Sender<()>>) -> zbus::Result<()> {
+        let conn = Connection::session()?;
+        let rules = [
             MatchRule::builder()
                 .msg_type(MessageType::Signal)
                 .interface(PROPERTIES_INTERFACE)?
                 .member("PropertiesChanged")?
                 .path(OBJECT_PATH)?
                 .build(),
-        )?;
-
-        dbus.add_match_rule(
             MatchRule::builder()
                 .msg_type(MessageType::Signal)
                 .sender(DBUS_INTERFACE)?
@@ -358,16 +373,17 @@ impl Monitor {
                 .member("NameOwnerChanged")?
                 .arg0ns(BUS_NAMESPACE)?
                 .build(),
-        )?;
-
-        dbus.add_match_rule(
             MatchRule::builder()
                 .msg_type(MessageType::Signal)
                 .interface(PLAYER_INTERFACE)?
                 .member("Seeked")?
                 .path(OBJECT_PATH)?
                 .build(),
-        )?;
+        ];
+        if !self.handle.subscribe(&conn, rules)? {
+            return Ok(());
+        }
+        let dbus = DBusProxy::new(&conn)?;
 
         for name in dbus.list_names()?.iter() {
             let name = name.as_str();
@@ -381,12 +397,20 @@ impl Monitor {
                 self.add(&conn, name, owner.to_string());
             }
         }
-        self.refresh();
+        match first.take() {
+            // the first update waiting for the players while it holds the
+            // blocks is not asked to update again, unless it gave up waiting
+            Some(first) => {
+                let changed = self.store();
+                if first.send(()).is_err() && changed {
+                    self.update();
+                }
+            }
+            None => self.refresh(),
+        }
 
-        for message in MessageIterator::from(&conn) {
-            let Ok(message) = message else {
-                continue;
-            };
+        let handle = self.handle.clone();
+        for message in handle.messages(&conn) {
             let header = message.header();
             let (Some(interface), Some(member)) = (header.interface(), header.member()) else {
                 continue;
@@ -442,26 +466,38 @@ impl Monitor {
 
 pub struct MprisService {
     cache: Arc<Mutex<MprisCache>>,
+    _listener: Listener,
 }
 
 impl MprisService {
+    /// the block is updated once the players are known, only a single
+    /// status line waits a bit for them since the blocks are locked
     pub fn new(block_index: usize, priority: Vec<String>) -> Self {
         let cache = Arc::new(Mutex::new(MprisCache::new()));
         let thread_cache = cache.clone();
-        thread::spawn(move || {
-            let mut first = true;
-            loop {
-                if !first {
-                    thread::sleep(std::time::Duration::from_secs(RECONNECT_DELAY_SECS));
-                }
-                first = false;
-                let mut monitor = Monitor::new(block_index, thread_cache.clone(), priority.clone());
-                let _ = monitor.run();
-                monitor.players.clear();
-                monitor.refresh();
-            }
+        let (sender, receiver) = mpsc::channel();
+        let wait = worker::inline();
+        let mut first = wait.then_some(sender);
+        let listener = Listener::spawn(RECONNECT_DELAY, move |handle| {
+            let mut monitor = Monitor::new(
+                block_index,
+                thread_cache.clone(),
+                priority.clone(),
+                handle.clone(),
+            );
+            let _ = monitor.run(&mut first);
+            // no bus at all, the first update is not kept waiting
+            first = None;
+            monitor.players.clear();
+            monitor.refresh();
         });
-        MprisService { cache }
+        if wait {
+            let _ = receiver.recv_timeout(FIRST_FETCH_TIMEOUT);
+        }
+        MprisService {
+            cache,
+            _listener: listener,
+        }
     }
 
     pub fn snapshot(&self) -> Option<Snapshot> {
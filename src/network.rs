@@ -1,7 +1,11 @@
 use super::base::{Base, Value};
 use super::block;
+use super::template::Field;
 use serde::Deserialize;
 
+/// options of the block, Base ones excluded
+pub const FIELDS: &[&str] = &["wifi", "ethernet"];
+
 #[derive(Deserialize)]
 pub struct Block {
     #[serde(flatten)]
@@ -51,6 +55,22 @@ fn get_wifi_strength(iname: &str) -> Result<u32, ()> {
     Ok(PERC_LUT[index])
 }
 
+fn get_ssid(iname: &str) -> Option<String> {
+    neli_wifi::Socket::connect()
+        .ok()?
+        .get_interfaces_info()
+        .ok()?
+        .into_iter()
+        .find(|interface| interface.name.as_deref().map(trim_nul) == Some(iname.as_bytes()))?
+        .ssid
+        .map(|ssid| String::from_utf8_lossy(trim_nul(&ssid)).into_owned())
+}
+
+/// netlink strings may come nul terminated
+fn trim_nul(bytes: &[u8]) -> &[u8] {
+    bytes.strip_suffix(&[0]).unwrap_or(bytes)
+}
+
 fn get_active_interface() -> Result<String, ()> {
     if let Ok(text) = std::fs::read_to_string("/proc/net/route") {
         let mut lowest_metric = 0xffff;
@@ -77,14 +97,29 @@ impl block::Block for Block {
 
     fn update(&mut self) {
         self.base.value = Value::Invalid;
+        self.base.placeholders.clear();
         if let Ok(iface) = get_active_interface() {
+            self.base
+                .placeholders
+                .push(("iface", Field::from(iface.as_str())));
             if is_wireless(&iface) {
                 self.base.set_prefix(&self.wifi);
                 self.base.set_suffix("%");
-                self.base.value = Value::new(get_wifi_strength(&iface));
+                let strength = get_wifi_strength(&iface);
+                self.base.placeholders.extend([
+                    ("ssid", Field::from(get_ssid(&iface).unwrap_or_default())),
+                    (
+                        "strength",
+                        strength.map_or_else(|_| Field::from(""), Field::from),
+                    ),
+                ]);
+                self.base.value = Value::new(strength);
             } else {
                 self.base.set_prefix("");
                 self.base.set_suffix("");
+                self.base
+                    .placeholders
+                    .extend([("ssid", Field::from("")), ("strength", Field::from(""))]);
                 self.base.value = Value::new(self.ethernet.clone());
             }
         }
@@ -1,12 +1,17 @@
 use super::blocks::update_by_index;
 use super::sound_service::SoundService;
+use super::worker;
 
 use std::{
     cell::RefCell,
     collections::HashMap,
     rc::Rc,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
     thread,
+    time::Duration,
 };
 
 use pipewire as pw;
@@ -20,6 +25,8 @@ use pw::spa::utils::dict::DictRef;
 use pw::types::ObjectType;
 
 const RECONNECT_DELAY_MS: u64 = 20;
+/// how long the first update of a single status line waits for the volume
+const FIRST_FETCH_TIMEOUT: Duration = Duration::from_secs(1);
 
 #[derive(Copy, Clone)]
 struct Volume {
@@ -90,7 +97,8 @@ impl<T> Proxy<T> {
 struct Sink {
     name: String,
     card_name: String,
-    volume: Volume,
+    /// None until the node tells its props
+    volume: Option<Volume>,
     node: Proxy<Node>,
 }
 
@@ -99,7 +107,7 @@ impl Sink {
         Sink {
             name,
             card_name,
-            volume: Volume::new(),
+            volume: None,
             node,
         }
     }
@@ -107,11 +115,14 @@ impl Sink {
 
 struct MonitorData {
     block_index: usize,
+    alive: Arc<AtomicBool>,
     sinks: HashMap<u32, Sink>,
     cache: Arc<Mutex<PipewireCache>>,
     meta: Option<Proxy<Metadata>>,
     def_sink: Option<String>,
     def_sink_id: u32,
+    /// the first update waiting for the volume, if it does
+    first: Option<mpsc::Sender<()>>,
 }
 
 struct Monitor {
@@ -119,14 +130,43 @@ struct Monitor {
 }
 
 impl MonitorData {
-    fn new(block_index: usize, cache: Arc<Mutex<PipewireCache>>) -> Self {
+    fn new(
+        block_index: usize,
+        alive: Arc<AtomicBool>,
+        cache: Arc<Mutex<PipewireCache>>,
+        first: Option<mpsc::Sender<()>>,
+    ) -> Self {
         Self {
             block_index,
+            alive,
             sinks: HashMap::new(),
             cache,
             meta: None,
             def_sink: None,
             def_sink_id: 0,
+            first,
+        }
+    }
+
+    /// known tells whether the volume of the default sink is in the cache
+    fn notify(&mut self, known: bool) {
+        match self.first.take() {
+            // the first update waiting for the volume while it holds the
+            // blocks is not asked to update again, unless it gave up waiting
+            Some(first) if known => {
+                if first.send(()).is_ok() {
+                    return;
+                }
+            }
+            // updating now would block the loop the volume comes from
+            Some(first) => {
+                self.first = Some(first);
+                return;
+            }
+            None => {}
+        }
+        if self.alive.load(Ordering::Acquire) {
+            update_by_index(self.block_index);
         }
     }
 
@@ -138,11 +178,14 @@ impl MonitorData {
         if let Some(def_sink) = self.def_sink.as_ref() {
             if let Some(sink) = self.sinks.iter().find(|s| &s.1.name == def_sink) {
                 self.def_sink_id = *sink.0;
+                let volume = sink.1.volume;
                 if let Ok(mut cache) = self.cache.lock() {
                     cache.update_name(sink.1.card_name.to_owned());
-                    cache.update_vol(sink.1.volume);
+                    if let Some(volume) = volume {
+                        cache.update_vol(volume);
+                    }
                 }
-                update_by_index(self.block_index);
+                self.notify(volume.is_some());
             }
         }
     }
@@ -157,19 +200,29 @@ impl MonitorData {
 
     fn update_volume(&mut self, id: u32, volume: Volume) {
         self.sinks.entry(id).and_modify(|s| {
-            s.volume = volume;
+            s.volume = Some(volume);
         });
         if id == self.def_sink_id {
             self.cache.lock().unwrap().update_vol(volume);
-            update_by_index(self.block_index);
+            self.notify(true);
         }
     }
 }
 
 impl Monitor {
-    fn new(block_index: usize, cache: Arc<Mutex<PipewireCache>>) -> Self {
+    fn new(
+        block_index: usize,
+        alive: Arc<AtomicBool>,
+        cache: Arc<Mutex<PipewireCache>>,
+        first: Option<mpsc::Sender<()>>,
+    ) -> Self {
         Monitor {
-            data: Rc::new(RefCell::new(MonitorData::new(block_index, cache))),
+            data: Rc::new(RefCell::new(MonitorData::new(
+                block_index,
+                alive,
+                cache,
+                first,
+            ))),
         }
     }
 
@@ -285,22 +338,34 @@ impl Monitor {
     }
 }
 
+/// sender quitting the main loop of the running monitor
+type SharedQuit = Arc<Mutex<Option<pw::channel::Sender<()>>>>;
+
 pub struct PipewireDevice {
     cache: Arc<Mutex<PipewireCache>>,
+    alive: Arc<AtomicBool>,
+    quit: SharedQuit,
 }
 
 impl PipewireDevice {
     pub fn new() -> Option<PipewireDevice> {
         pw::init();
         let cache = Arc::new(Mutex::new(PipewireCache::new()));
-        Some(PipewireDevice { cache })
+        Some(PipewireDevice {
+            cache,
+            alive: Arc::new(AtomicBool::new(true)),
+            quit: Arc::new(Mutex::new(None)),
+        })
     }
 }
 
 impl Drop for PipewireDevice {
+    // pipewire is never deinitialized: pw::init() works only once per
+    // process, and a reloaded config creates a new device
     fn drop(&mut self) {
-        unsafe {
-            pw::deinit();
+        self.alive.store(false, Ordering::Release);
+        if let Some(quit) = self.quit.lock().unwrap().take() {
+            let _ = quit.send(());
         }
     }
 }
@@ -314,67 +379,95 @@ impl SoundService for PipewireDevice {
         self.cache.lock().unwrap().sink_name.clone()
     }
 
+    /// only a single status line waits a bit for the volume, since the
+    /// blocks are locked meanwhile
     fn listen(&mut self, block_index: usize) {
         let cache2 = self.cache.clone();
-        thread::spawn(move || loop {
-            thread::sleep(std::time::Duration::from_millis(RECONNECT_DELAY_MS));
-            let cache_clone = cache2.clone();
-            let monitor = Monitor::new(block_index, cache_clone);
-            let main_loop = match pw::main_loop::MainLoopRc::new(None) {
-                Ok(l) => l,
-                Err(_) => continue,
-            };
-            let context = match pw::context::ContextRc::new(&main_loop, None) {
-                Ok(c) => c,
-                Err(_) => continue,
-            };
-            let main_loop_weak = main_loop.downgrade();
-            let core = match context.connect_rc(None) {
-                Ok(c) => c,
-                Err(_) => continue,
-            };
-            let _core_listener = core
-                .add_listener_local()
-                .error(move |_, _, _, _| {
-                    if let Some(main_loop) = main_loop_weak.upgrade() {
+        let alive = self.alive.clone();
+        let quit = self.quit.clone();
+        let (sender, receiver) = mpsc::channel();
+        let wait = worker::inline();
+        let mut first = wait.then_some(sender);
+        thread::spawn(move || {
+            while alive.load(Ordering::Acquire) {
+                thread::sleep(std::time::Duration::from_millis(RECONNECT_DELAY_MS));
+                let cache_clone = cache2.clone();
+                // dropped with the monitor if pipewire is not there, the
+                // first update is not kept waiting then
+                let monitor = Monitor::new(block_index, alive.clone(), cache_clone, first.take());
+                let main_loop = match pw::main_loop::MainLoopRc::new(None) {
+                    Ok(l) => l,
+                    Err(_) => continue,
+                };
+                let (quit_sender, quit_receiver) = pw::channel::channel::<()>();
+                let main_loop_quit = main_loop.downgrade();
+                let _quit_receiver = quit_receiver.attach(main_loop.loop_(), move |_| {
+                    if let Some(main_loop) = main_loop_quit.upgrade() {
                         main_loop.quit();
                     }
-                })
-                .register();
-
-            let registry = match core.get_registry_rc() {
-                Ok(r) => r,
-                Err(_) => continue,
-            };
-            let registry_weak = registry.downgrade();
-
-            let check_prop = |props: &Option<&DictRef>, key: &str, value: &str| -> bool {
-                props.is_some_and(|p| p.get(key) == Some(value))
-            };
-            let _reg = registry
-                .add_listener_local()
-                .global(move |obj| {
-                    if let Some(registry) = registry_weak.upgrade() {
-                        match obj.type_ {
-                            ObjectType::Node => {
-                                if !check_prop(&obj.props, "media.class", "Audio/Sink") {
-                                    return;
+                });
+                {
+                    let mut shared = quit.lock().unwrap();
+                    if !alive.load(Ordering::Acquire) {
+                        break;
+                    }
+                    *shared = Some(quit_sender);
+                }
+                let context = match pw::context::ContextRc::new(&main_loop, None) {
+                    Ok(c) => c,
+                    Err(_) => continue,
+                };
+                let main_loop_weak = main_loop.downgrade();
+                let core = match context.connect_rc(None) {
+                    Ok(c) => c,
+                    Err(_) => continue,
+                };
+                let _core_listener = core
+                    .add_listener_local()
+                    .error(move |_, _, _, _| {
+                        if let Some(main_loop) = main_loop_weak.upgrade() {
+                            main_loop.quit();
+                        }
+                    })
+                    .register();
+
+                let registry = match core.get_registry_rc() {
+                    Ok(r) => r,
+                    Err(_) => continue,
+                };
+                let registry_weak = registry.downgrade();
+
+                let check_prop = |props: &Option<&DictRef>, key: &str, value: &str| -> bool {
+                    props.is_some_and(|p| p.get(key) == Some(value))
+                };
+                let _reg = registry
+                    .add_listener_local()
+                    .global(move |obj| {
+                        if let Some(registry) = registry_weak.upgrade() {
+                            match obj.type_ {
+                                ObjectType::Node => {
+                                    if !check_prop(&obj.props, "media.class", "Audio/Sink") {
+                                        return;
+                                    }
+                                    monitor.add_sink(&registry, obj);
                                 }
-                                monitor.add_sink(&registry, obj);
-                            }
-                            ObjectType::Metadata => {
-                                if !check_prop(&obj.props, "metadata.name", "default") {
-                                    return;
+                                ObjectType::Metadata => {
+                                    if !check_prop(&obj.props, "metadata.name", "default") {
+                                        return;
+                                    }
+                                    monitor.set_meta(&registry, obj);
                                 }
-                                monitor.set_meta(&registry, obj);
-                            }
-                            _ => {}
-                        };
-                    }
-                })
-                .register();
-            main_loop.run();
+                                _ => {}
+                            };
+                        }
+                    })
+                    .register();
+                main_loop.run();
+            }
         });
+        if wait {
+            let _ = receiver.recv_timeout(FIRST_FETCH_TIMEOUT);
+        }
     }
 
     fn volume(&self, _mixer: &str) -> Option<u32> {
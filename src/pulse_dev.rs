@@ -81,10 +81,11 @@ impl Drop for MainloopLock<'_> {
     }
 }
 
+// the context has to be freed before the main loop, hence the field order
 pub struct PulseDevice {
-    dispatcher: Rc<RefCell<Mainloop>>,
     context: Rc<RefCell<Context>>,
     cache: Rc<RefCell<PulseCache>>,
+    dispatcher: Rc<RefCell<Mainloop>>,
 }
 
 impl PulseDevice {
@@ -115,9 +116,9 @@ impl PulseDevice {
                     .is_ok()
                 {
                     return Some(PulseDevice {
-                        dispatcher,
                         context,
                         cache: Rc::new(RefCell::new(PulseCache::new())),
+                        dispatcher,
                     });
                 }
             }
@@ -209,3 +210,16 @@ impl SoundService for PulseDevice {
 
     fn set_mute(&self, _mixer: &str, _value: bool) {}
 }
+
+impl Drop for PulseDevice {
+    fn drop(&mut self) {
+        {
+            let _lock = MainloopLock::new(&self.dispatcher);
+            let mut context = self.context.borrow_mut();
+            // the subscribe callback owns a reference to the context
+            context.set_subscribe_callback(None);
+            context.disconnect();
+        }
+        self.dispatcher.borrow_mut().stop();
+    }
+}
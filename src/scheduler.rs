@@ -0,0 +1,165 @@
+use chrono::Local;
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    mem::zeroed,
+    ptr,
+    sync::{Condvar, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// aligned updates are a bit late on purpose, so the wall clock has surely
+/// crossed the boundary when the block is updated
+const ALIGN_SLACK: Duration = Duration::from_millis(5);
+
+/// update period of a block
+#[derive(Clone, Copy)]
+pub struct Timer {
+    /// zero if the timer does not drive the block
+    pub interval: Duration,
+    /// update on wall clock multiples of the interval
+    pub align: bool,
+}
+
+impl Timer {
+    /// next update after the one due at deadline, now is the current time
+    fn next(&self, deadline: Instant, now: Instant) -> Instant {
+        if self.align {
+            return now + until_boundary(self.interval) + ALIGN_SLACK;
+        }
+        // a late wakeup skips the missed updates instead of bursting them
+        let next = deadline + self.interval;
+        if next <= now {
+            now + self.interval
+        } else {
+            next
+        }
+    }
+}
+
+/// time left until the local wall clock reaches the next multiple of interval
+fn until_boundary(interval: Duration) -> Duration {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let offset = Local::now().offset().local_minus_utc() as i128 * 1_000_000_000;
+    let local = since_epoch.as_nanos() as i128 + offset;
+    let interval = interval.as_nanos() as i128;
+    let left = interval - local.rem_euclid(interval);
+    Duration::from_nanos(left as u64)
+}
+
+struct State {
+    /// next update of every timer driven block, earliest first
+    deadlines: BinaryHeap<Reverse<(Instant, usize)>>,
+    timers: Vec<Timer>,
+    redraw: bool,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    deadlines: BinaryHeap::new(),
+    timers: Vec::new(),
+    redraw: false,
+});
+static WAKEUP: Condvar = Condvar::new();
+
+/// replace the schedule, the first updates are due one interval from now,
+/// or at the next boundary for aligned blocks
+pub fn reset(timers: Vec<Timer>) {
+    let now = Instant::now();
+    let mut state = STATE.lock().unwrap();
+    state.deadlines = timers
+        .iter()
+        .enumerate()
+        .filter(|(_, timer)| !timer.interval.is_zero())
+        .map(|(index, timer)| Reverse((timer.next(now, now), index)))
+        .collect();
+    state.timers = timers;
+    state.redraw = true;
+    WAKEUP.notify_one();
+}
+
+/// make every timer driven block due right away, their deadlines are
+/// meaningless after the wall clock jumped or the system slept
+fn resync() {
+    let now = Instant::now();
+    let mut state = STATE.lock().unwrap();
+    state.deadlines = state
+        .deadlines
+        .drain()
+        .map(|Reverse((_, index))| Reverse((now, index)))
+        .collect();
+    WAKEUP.notify_one();
+}
+
+/// resync the schedule whenever the wall clock is set, the kernel reports
+/// the resume from suspend the same way
+pub fn watch_clock() {
+    thread::spawn(|| unsafe {
+        let fd = libc::timerfd_create(libc::CLOCK_REALTIME, libc::TFD_CLOEXEC);
+        if fd < 0 {
+            return;
+        }
+        // the timer itself never fires, it only gets cancelled by clock changes
+        let mut spec: libc::itimerspec = zeroed();
+        spec.it_value.tv_sec = libc::time_t::MAX;
+        let flags = libc::TFD_TIMER_ABSTIME | libc::TFD_TIMER_CANCEL_ON_SET;
+        while libc::timerfd_settime(fd, flags, &spec, ptr::null_mut()) == 0 {
+            let mut expirations = 0u64;
+            let read = libc::read(
+                fd,
+                &mut expirations as *mut u64 as *mut libc::c_void,
+                std::mem::size_of::<u64>(),
+            );
+            if read >= 0 {
+                break;
+            }
+            match std::io::Error::last_os_error().raw_os_error() {
+                Some(libc::ECANCELED) => resync(),
+                Some(libc::EINTR) => {}
+                _ => break,
+            }
+        }
+        libc::close(fd);
+    });
+}
+
+/// ask the main loop to print the status line, requests made before it gets
+/// to it are merged into a single line
+pub fn redraw() {
+    STATE.lock().unwrap().redraw = true;
+    WAKEUP.notify_one();
+}
+
+/// sleep until either some blocks are due or a redraw is requested, returns
+/// the indexes of the due blocks, the caller is expected to redraw afterwards
+pub fn wait() -> Vec<usize> {
+    let mut state = STATE.lock().unwrap();
+    loop {
+        let now = Instant::now();
+        let mut due = Vec::new();
+        while let Some(Reverse((deadline, index))) = state.deadlines.peek().copied() {
+            if deadline > now {
+                break;
+            }
+            state.deadlines.pop();
+            due.push(index);
+            let next = state.timers[index].next(deadline, now);
+            state.deadlines.push(Reverse((next, index)));
+        }
+
+        if !due.is_empty() || state.redraw {
+            state.redraw = false;
+            return due;
+        }
+
+        state = match state.deadlines.peek() {
+            Some(Reverse((deadline, _))) => {
+                let timeout = deadline.saturating_duration_since(now);
+                WAKEUP.wait_timeout(state, timeout).unwrap().0
+            }
+            None => WAKEUP.wait(state).unwrap(),
+        };
+    }
+}
@@ -1,8 +1,12 @@
 use super::base::{Base, Value};
 use super::block;
+use super::utility::matches;
 use serde::Deserialize;
 use std::path::PathBuf;
 
+/// options of the block, Base ones excluded
+pub const FIELDS: &[&str] = &["sensor"];
+
 #[derive(Deserialize)]
 pub struct Block {
     #[serde(flatten)]
@@ -28,41 +32,17 @@ impl block::Block for Block {
             }
         };
 
-        self.base.value = Value::new(
-            inputs
-                .iter()
-                .filter_map(|path| std::fs::read_to_string(path).ok())
-                .filter_map(|text| text.trim().parse::<i32>().ok())
-                .max()
-                .map(|value| (value / 1000).max(0) as u32),
-        )
-    }
-}
-
-fn matches(mask: &str, name: &str) -> bool {
-    let m: Vec<char> = mask.chars().collect();
-    let n: Vec<char> = name.chars().collect();
-    let (mut mi, mut ni) = (0, 0);
-    let mut star = None;
-    let mut retry = 0;
-
-    while ni < n.len() {
-        if mi < m.len() && (m[mi] == '?' || m[mi] == n[ni]) {
-            mi += 1;
-            ni += 1;
-        } else if mi < m.len() && m[mi] == '*' {
-            star = Some(mi);
-            mi += 1;
-            retry = ni;
-        } else if let Some(pos) = star {
-            mi = pos + 1;
-            retry += 1;
-            ni = retry;
-        } else {
-            return false;
+        let millis = inputs
+            .iter()
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .filter_map(|text| text.trim().parse::<i32>().ok())
+            .max();
+        // whole degrees unless asked for decimals, so the width stays the same
+        self.base.value = match self.base.numbers().precision {
+            Some(_) => Value::new(millis.map(|value| value as f64 / 1000.0)),
+            None => Value::new(millis.map(|value| (value / 1000).max(0) as u32)),
         }
     }
-    m[mi..].iter().all(|c| *c == '*')
 }
 
 fn discover(mask: &str) -> Vec<PathBuf> {
@@ -0,0 +1,394 @@
+use super::utility::pango_escape;
+
+/// value of a placeholder
+#[derive(Clone)]
+pub enum Field {
+    /// an empty text counts as unresolved
+    Text(String),
+    Number(f64),
+    /// size in bytes
+    Bytes(u64),
+    /// inserted as is, neither escaped nor shortened
+    Raw(String),
+}
+
+impl From<&str> for Field {
+    fn from(text: &str) -> Self {
+        Field::Text(text.to_owned())
+    }
+}
+
+impl From<String> for Field {
+    fn from(text: String) -> Self {
+        Field::Text(text)
+    }
+}
+
+impl From<u32> for Field {
+    fn from(value: u32) -> Self {
+        Field::Number(value as f64)
+    }
+}
+
+impl From<f64> for Field {
+    fn from(value: f64) -> Self {
+        Field::Number(value)
+    }
+}
+
+/// how sizes are scaled to units
+#[derive(Clone, Copy, Default)]
+pub struct Units {
+    /// powers of 1000 (kB, MB...) instead of 1024 (KiB, MiB...)
+    pub si: bool,
+    /// fixed power of the unit, the largest one below the size if none
+    pub power: Option<usize>,
+}
+
+const IEC_UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+const SI_UNITS: [&str; 7] = ["B", "kB", "MB", "GB", "TB", "PB", "EB"];
+
+impl Units {
+    /// units of a name like GiB or MB
+    pub fn parse(name: &str) -> Option<Units> {
+        let name = if name == "KB" { "kB" } else { name };
+        if let Some(power) = IEC_UNITS.iter().position(|unit| *unit == name) {
+            return Some(Units {
+                si: false,
+                power: Some(power),
+            });
+        }
+        SI_UNITS
+            .iter()
+            .position(|unit| *unit == name)
+            .map(|power| Units {
+                si: true,
+                power: Some(power),
+            })
+    }
+}
+
+/// how numbers are shown unless a placeholder says otherwise
+#[derive(Clone, Copy, Default)]
+pub struct Numbers {
+    /// digits after the decimal point
+    pub precision: Option<usize>,
+    pub units: Units,
+}
+
+pub enum Resolved {
+    Value(Field),
+    Unresolved,
+    Unknown,
+}
+
+/// placeholders of a block besides the value, set on every update
+pub type Placeholders = Vec<(&'static str, Field)>;
+
+pub fn lookup(placeholders: &Placeholders, name: &str) -> Resolved {
+    match placeholders.iter().find(|(known, _)| *known == name) {
+        Some((_, field)) => Resolved::Value(field.clone()),
+        None => Resolved::Unknown,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// modifiers after the colon of a placeholder, like {name:>8.2}
+#[derive(Clone, Copy)]
+struct Spec {
+    fill: char,
+    align: Option<Align>,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+fn align_of(c: char) -> Option<Align> {
+    match c {
+        '<' => Some(Align::Left),
+        '^' => Some(Align::Center),
+        '>' => Some(Align::Right),
+        _ => None,
+    }
+}
+
+fn number_at(chars: &[char]) -> (Option<usize>, &[char]) {
+    let digits = chars.iter().take_while(|c| c.is_ascii_digit()).count();
+    let number = chars[..digits].iter().collect::<String>().parse().ok();
+    (number, &chars[digits..])
+}
+
+impl Spec {
+    /// [[fill]align][0][width][.precision]
+    fn parse(text: &str) -> Option<Spec> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut spec = Spec {
+            fill: ' ',
+            align: None,
+            zero: false,
+            width: 0,
+            precision: None,
+        };
+
+        let mut rest = &chars[..];
+        if let Some(align) = rest.get(1).and_then(|c| align_of(*c)) {
+            spec.fill = rest[0];
+            spec.align = Some(align);
+            rest = &rest[2..];
+        } else if let Some(align) = rest.first().and_then(|c| align_of(*c)) {
+            spec.align = Some(align);
+            rest = &rest[1..];
+        }
+        if let Some(tail) = rest.strip_prefix(&['0']) {
+            spec.zero = true;
+            rest = tail;
+        }
+        let (width, tail) = number_at(rest);
+        spec.width = width.unwrap_or(0);
+        rest = tail;
+        if let Some(tail) = rest.strip_prefix(&['.']) {
+            let (precision, tail) = number_at(tail);
+            spec.precision = Some(precision?);
+            rest = tail;
+        }
+        rest.is_empty().then_some(spec)
+    }
+
+    fn pad(&self, text: String, numeric: bool) -> String {
+        let length = text.chars().count();
+        if length >= self.width {
+            return text;
+        }
+        // zeros go between the sign and the digits
+        if let (true, Some(digits)) = (self.zero && numeric, text.strip_prefix('-')) {
+            let spec = Spec {
+                width: self.width - 1,
+                ..*self
+            };
+            return format!("-{}", spec.pad(digits.to_owned(), true));
+        }
+        let (fill, default_align) = match (self.zero, numeric) {
+            (true, _) => ('0', Align::Right),
+            (false, true) => (self.fill, Align::Right),
+            (false, false) => (self.fill, Align::Left),
+        };
+        let missing = self.width - length;
+        let (left, right) = match self.align.unwrap_or(default_align) {
+            Align::Left => (0, missing),
+            Align::Center => (missing / 2, missing - missing / 2),
+            Align::Right => (missing, 0),
+        };
+        let fill = |count| std::iter::repeat_n(fill, count).collect::<String>();
+        fill(left) + &text + &fill(right)
+    }
+}
+
+/// whole numbers are shown as such, fractions with a single decimal
+fn number(value: f64, precision: Option<usize>) -> String {
+    let precision = precision.unwrap_or(if value.fract() == 0.0 { 0 } else { 1 });
+    format!("{:.*}", precision, value)
+}
+
+fn bytes(value: u64, precision: Option<usize>, units: Units) -> String {
+    let (base, names) = match units.si {
+        true => (1000.0, SI_UNITS),
+        false => (1024.0, IEC_UNITS),
+    };
+    let mut scaled = value as f64;
+    let mut unit = 0;
+    match units.power {
+        Some(power) => {
+            scaled /= f64::powi(base, power as i32);
+            unit = power;
+        }
+        None => {
+            while scaled >= base && unit + 1 < names.len() {
+                scaled /= base;
+                unit += 1;
+            }
+        }
+    }
+    let precision = precision.unwrap_or(if unit == 0 { 0 } else { 1 });
+    format!("{:.*}{}", precision, scaled, names[unit])
+}
+
+pub fn truncate(text: String, budget: usize) -> String {
+    if text.chars().count() <= budget {
+        return text;
+    }
+    if budget == 0 {
+        return String::new();
+    }
+    let mut result: String = text.chars().take(budget).collect();
+    result.push('…');
+    result
+}
+
+fn take(text: &str, budget: &mut usize) -> String {
+    if *budget == usize::MAX {
+        return text.to_owned();
+    }
+    let count = text.chars().count();
+    if count <= *budget {
+        *budget -= count;
+        return text.to_owned();
+    }
+    let result = truncate(text.to_owned(), *budget);
+    *budget = 0;
+    result
+}
+
+/// options shared by all the placeholders of a format
+struct Context<'a> {
+    resolve: &'a dyn Fn(&str) -> Resolved,
+    markup: bool,
+    numbers: Numbers,
+}
+
+/// the field as shown, None if it is an empty text
+fn show(field: Field, spec: &Spec, budget: &mut usize, context: &Context) -> Option<String> {
+    let escape = |text: String| {
+        if context.markup {
+            pango_escape(&text)
+        } else {
+            text
+        }
+    };
+    let precision = spec.precision.or(context.numbers.precision);
+
+    let text = match field {
+        Field::Text(text) if text.is_empty() => return None,
+        Field::Text(text) => {
+            let text = match spec.precision {
+                Some(precision) => text.chars().take(precision).collect(),
+                None => text,
+            };
+            escape(spec.pad(take(&text, budget), false))
+        }
+        Field::Number(value) => escape(spec.pad(number(value, precision), true)),
+        Field::Bytes(value) => {
+            escape(spec.pad(bytes(value, precision, context.numbers.units), true))
+        }
+        Field::Raw(text) => spec.pad(text, false),
+    };
+    Some(text)
+}
+
+/// a value shown on its own, padded with spaces or zeros up to width
+pub fn value(field: Field, width: usize, zero: bool, markup: bool, numbers: Numbers) -> String {
+    let spec = Spec {
+        fill: ' ',
+        align: None,
+        zero,
+        width,
+        precision: None,
+    };
+    let context = Context {
+        resolve: &|_| Resolved::Unknown,
+        markup,
+        numbers,
+    };
+    let mut budget = usize::MAX;
+    show(field, &spec, &mut budget, &context).unwrap_or_default()
+}
+
+enum Expanded {
+    Text(String),
+    Unresolved,
+    Unknown,
+}
+
+/// expand the inside of a {} pair
+fn expand(body: &str, budget: &mut usize, context: &Context) -> Expanded {
+    let (name, spec) = match body.split_once(':') {
+        Some((name, spec)) => match Spec::parse(spec) {
+            Some(spec) => (name, spec),
+            None => return Expanded::Unknown,
+        },
+        None => (body, Spec::parse("").expect("empty spec")),
+    };
+
+    match (context.resolve)(name) {
+        Resolved::Value(field) => match show(field, &spec, budget, context) {
+            Some(text) => Expanded::Text(text),
+            None => Expanded::Unresolved,
+        },
+        Resolved::Unresolved => Expanded::Unresolved,
+        Resolved::Unknown => Expanded::Unknown,
+    }
+}
+
+fn render_part(
+    format: &str,
+    budget: &mut usize,
+    grouped: bool,
+    context: &Context,
+) -> Option<String> {
+    let mut out = String::new();
+    let mut rest = format;
+    while !rest.is_empty() {
+        let Some(at) = rest.find(['{', '[']) else {
+            out.push_str(rest);
+            break;
+        };
+        out.push_str(&rest[..at]);
+        rest = &rest[at..];
+
+        if let Some(tail) = rest.strip_prefix('{') {
+            let Some(end) = tail.find('}') else {
+                out.push_str(rest);
+                break;
+            };
+            match expand(&tail[..end], budget, context) {
+                Expanded::Text(text) => out.push_str(&text),
+                Expanded::Unresolved if grouped => return None,
+                Expanded::Unresolved => {}
+                Expanded::Unknown => out.push_str(&rest[..end + 2]),
+            }
+            rest = &tail[end + 1..];
+        } else if grouped {
+            out.push('[');
+            rest = &rest[1..];
+        } else {
+            let Some(end) = rest.find(']') else {
+                out.push('[');
+                rest = &rest[1..];
+                continue;
+            };
+            let mut local = *budget;
+            if let Some(group) = render_part(&rest[1..end], &mut local, true, context) {
+                out.push('[');
+                out.push_str(&group);
+                out.push(']');
+                *budget = local;
+            }
+            rest = &rest[end + 1..];
+        }
+    }
+    Some(out)
+}
+
+/// substitute the placeholders of format. A text in [ ] is kept with its
+/// brackets if all of its placeholders resolve, and dropped otherwise.
+/// Texts are shortened to fit the budget, shared by all of them, and the
+/// values are escaped if the format is pango markup
+pub fn render(
+    format: &str,
+    resolve: &dyn Fn(&str) -> Resolved,
+    budget: &mut usize,
+    markup: bool,
+    numbers: Numbers,
+) -> String {
+    let context = Context {
+        resolve,
+        markup,
+        numbers,
+    };
+    render_part(format, budget, false, &context).unwrap_or_default()
+}
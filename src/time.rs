@@ -3,6 +3,9 @@ use super::block;
 use chrono::Local;
 use serde::Deserialize;
 
+/// options of the block, Base ones excluded
+pub const FIELDS: &[&str] = &["format"];
+
 #[derive(Deserialize)]
 pub struct Block {
     #[serde(flatten)]
@@ -14,7 +17,13 @@ pub struct Block {
 impl block::Block for Block {
     impl_Block!();
     fn update(&mut self) {
-        self.base.value = Value::new(format!("{}", Local::now().format(&self.format)));
+        let text = format!("{}", Local::now().format(&self.format));
+        // the format comes from the config, so it may carry markup
+        self.base.value = if self.base.markup() {
+            Value::Markup(text)
+        } else {
+            Value::new(text)
+        };
     }
 }
 
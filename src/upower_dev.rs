@@ -0,0 +1,320 @@
+use super::blocks::update_by_index;
+use super::dbus_listener::{Handle, Listener};
+use super::worker;
+
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+
+use zbus::{
+    blocking::{fdo::PropertiesProxy, Connection},
+    message::Type as MessageType,
+    names::InterfaceName,
+    zvariant::{OwnedValue, Value},
+    MatchRule,
+};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+/// how long the first update of a single status line waits for the state
+/// of the device
+const FIRST_FETCH_TIMEOUT: Duration = Duration::from_secs(1);
+const BUS_NAME: &str = "org.freedesktop.UPower";
+const UPOWER_PATH: &str = "/org/freedesktop/UPower";
+const DEVICES_PATH: &str = "/org/freedesktop/UPower/devices/";
+const UPOWER_INTERFACE: &str = "org.freedesktop.UPower";
+const DEVICE_INTERFACE: &str = "org.freedesktop.UPower.Device";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+const DBUS_INTERFACE: &str = "org.freedesktop.DBus";
+
+/// bus UPower is found on, the session one is meant for testing
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Bus {
+    #[default]
+    System,
+    Session,
+}
+
+/// state of a UPower device
+#[derive(Clone, PartialEq)]
+pub struct Device {
+    /// named like the sysfs statuses
+    pub status: &'static str,
+    pub percentage: f64,
+    /// energy in watt hours and power in watts
+    pub energy: f64,
+    pub energy_full: f64,
+    pub energy_rate: f64,
+    /// the AC line is unplugged, None if UPower does not tell
+    pub on_battery: Option<bool>,
+}
+
+/// the UPower device state as a sysfs status
+fn status_name(state: u32) -> &'static str {
+    match state {
+        1 => "Charging",
+        2 | 3 => "Discharging",
+        4 => "Full",
+        5 | 6 => "Not charging",
+        _ => "Unknown",
+    }
+}
+
+fn as_f64(value: &Value<'_>) -> Option<f64> {
+    match value {
+        Value::F64(number) => Some(*number),
+        Value::Value(inner) => as_f64(inner),
+        _ => None,
+    }
+}
+
+fn as_u32(value: &Value<'_>) -> Option<u32> {
+    match value {
+        Value::U32(number) => Some(*number),
+        Value::Value(inner) => as_u32(inner),
+        _ => None,
+    }
+}
+
+fn as_bool(value: &Value<'_>) -> Option<bool> {
+    match value {
+        Value::Bool(flag) => Some(*flag),
+        Value::Value(inner) => as_bool(inner),
+        _ => None,
+    }
+}
+
+fn fetch_props(
+    conn: &Connection,
+    path: &str,
+    interface: &str,
+) -> Option<HashMap<String, OwnedValue>> {
+    let proxy = PropertiesProxy::builder(conn)
+        .destination(BUS_NAME)
+        .ok()?
+        .path(path.to_owned())
+        .ok()?
+        .build()
+        .ok()?;
+    proxy.get_all(InterfaceName::try_from(interface).ok()?).ok()
+}
+
+/// the device state, None if the device is missing or UPower is not running
+type Cache = Arc<Mutex<Option<Device>>>;
+
+struct Monitor {
+    block_index: usize,
+    path: String,
+    cache: Cache,
+    device: Option<Device>,
+    on_battery: Option<bool>,
+    handle: Handle,
+}
+
+impl Monitor {
+    fn apply_device(&mut self, props: &HashMap<String, OwnedValue>) {
+        let Some(ref mut device) = self.device else {
+            return;
+        };
+        if let Some(state) = props.get("State").and_then(|v| as_u32(v)) {
+            device.status = status_name(state);
+        }
+        if let Some(percentage) = props.get("Percentage").and_then(|v| as_f64(v)) {
+            device.percentage = percentage;
+        }
+        if let Some(energy) = props.get("Energy").and_then(|v| as_f64(v)) {
+            device.energy = energy;
+        }
+        if let Some(energy_full) = props.get("EnergyFull").and_then(|v| as_f64(v)) {
+            device.energy_full = energy_full;
+        }
+        if let Some(energy_rate) = props.get("EnergyRate").and_then(|v| as_f64(v)) {
+            device.energy_rate = energy_rate;
+        }
+        if props.get("IsPresent").and_then(|v| as_bool(v)) == Some(false) {
+            self.device = None;
+        }
+    }
+
+    /// read the whole state, done on start and whenever UPower restarts
+    fn fetch(&mut self, conn: &Connection) {
+        self.on_battery = fetch_props(conn, UPOWER_PATH, UPOWER_INTERFACE)
+            .and_then(|props| props.get("OnBattery").and_then(|v| as_bool(v)));
+        self.device = None;
+        if let Some(props) = fetch_props(conn, &self.path, DEVICE_INTERFACE) {
+            self.device = Some(Device {
+                status: status_name(0),
+                percentage: 0.,
+                energy: 0.,
+                energy_full: 0.,
+                energy_rate: 0.,
+                on_battery: None,
+            });
+            self.apply_device(&props);
+        }
+    }
+
+    fn snapshot(&self) -> Option<Device> {
+        self.device.clone().map(|device| Device {
+            on_battery: self.on_battery,
+            ..device
+        })
+    }
+
+    fn refresh(&self) {
+        let device = self.snapshot();
+        {
+            let mut cache = self.cache.lock().unwrap();
+            if *cache == device {
+                return;
+            }
+            *cache = device;
+        }
+        self.update();
+    }
+
+    fn update(&self) {
+        if self.handle.alive() {
+            update_by_index(self.block_index);
+        }
+    }
+
+    fn run(&mut self, bus: Bus, first: &mut Option<mpsc::Sender<()>>) -> zbus::Result<()> {
+        let conn = match bus {
+            Bus::System => Connection::system()?,
+            Bus::Session => Connection::session()?,
+        };
+        let rules = [
+            MatchRule::builder()
+                .msg_type(MessageType::Signal)
+                .sender(BUS_NAME)?
+                .interface(PROPERTIES_INTERFACE)?
+                .member("PropertiesChanged")?
+                .build(),
+            MatchRule::builder()
+                .msg_type(MessageType::Signal)
+                .sender(DBUS_INTERFACE)?
+                .interface(DBUS_INTERFACE)?
+                .member("NameOwnerChanged")?
+                .arg(0, BUS_NAME)?
+                .build(),
+        ];
+        if !self.handle.subscribe(&conn, rules)? {
+            return Ok(());
+        }
+
+        self.fetch(&conn);
+        match first.take() {
+            // the first update waiting for this state while it holds the
+            // blocks is not asked to update again, unless it gave up waiting
+            Some(first) => {
+                *self.cache.lock().unwrap() = self.snapshot();
+                if first.send(()).is_err() {
+                    self.update();
+                }
+            }
+            None => self.refresh(),
+        }
+
+        let handle = self.handle.clone();
+        for message in handle.messages(&conn) {
+            let header = message.header();
+            let (Some(interface), Some(member)) = (header.interface(), header.member()) else {
+                continue;
+            };
+            let body = message.body();
+
+            if interface == PROPERTIES_INTERFACE && member == "PropertiesChanged" {
+                let Some(path) = header.path().map(|path| path.to_string()) else {
+                    continue;
+                };
+                let Ok((iface, changed, _invalidated)) =
+                    body.deserialize::<(String, HashMap<String, OwnedValue>, Vec<String>)>()
+                else {
+                    continue;
+                };
+                if path == self.path && iface == DEVICE_INTERFACE {
+                    if self.device.is_none() {
+                        // the device came back, IsPresent alone tells little
+                        self.fetch(&conn);
+                    } else {
+                        self.apply_device(&changed);
+                    }
+                } else if path == UPOWER_PATH && iface == UPOWER_INTERFACE {
+                    if let Some(on_battery) = changed.get("OnBattery").and_then(|v| as_bool(v)) {
+                        self.on_battery = Some(on_battery);
+                    }
+                } else {
+                    continue;
+                }
+                self.refresh();
+            } else if interface == DBUS_INTERFACE && member == "NameOwnerChanged" {
+                let Ok((name, _old, new)) = body.deserialize::<(String, String, String)>() else {
+                    continue;
+                };
+                if name != BUS_NAME {
+                    continue;
+                }
+                if new.is_empty() {
+                    self.device = None;
+                    self.on_battery = None;
+                } else {
+                    self.fetch(&conn);
+                }
+                self.refresh();
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct UPowerService {
+    cache: Cache,
+    _listener: Listener,
+}
+
+impl UPowerService {
+    /// watch the device, a name like DisplayDevice or battery_BAT0, or an
+    /// object path. The block is updated once the state is known, only a
+    /// single status line waits a bit for it since the blocks are locked
+    pub fn new(block_index: usize, bus: Bus, device: &str) -> Self {
+        let path = match device.starts_with('/') {
+            true => device.to_owned(),
+            false => DEVICES_PATH.to_owned() + device,
+        };
+        let cache: Cache = Arc::new(Mutex::new(None));
+        let thread_cache = cache.clone();
+        let (sender, receiver) = mpsc::channel();
+        let wait = worker::inline();
+        let mut first = wait.then_some(sender);
+        let listener = Listener::spawn(RECONNECT_DELAY, move |handle| {
+            let mut monitor = Monitor {
+                block_index,
+                path: path.clone(),
+                cache: thread_cache.clone(),
+                device: None,
+                on_battery: None,
+                handle: handle.clone(),
+            };
+            let _ = monitor.run(bus, &mut first);
+            // no bus at all, the first update is not kept waiting
+            first = None;
+            monitor.device = None;
+            monitor.refresh();
+        });
+        if wait {
+            let _ = receiver.recv_timeout(FIRST_FETCH_TIMEOUT);
+        }
+        UPowerService {
+            cache,
+            _listener: listener,
+        }
+    }
+
+    pub fn device(&self) -> Option<Device> {
+        self.cache.lock().unwrap().clone()
+    }
+}
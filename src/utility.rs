@@ -2,8 +2,10 @@ use std::{
     fs, io,
     mem::{zeroed, MaybeUninit},
     ptr, str,
+    time::Duration,
 };
 pub const SIGRTMIN: i32 = 34;
+pub const SIGRTMAX: i32 = 64;
 
 pub fn read_filen(filename: &str, max: usize) -> Result<String, io::Error> {
     use std::io::Read;
@@ -13,26 +15,53 @@ pub fn read_filen(filename: &str, max: usize) -> Result<String, io::Error> {
     String::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
 }
 
-pub fn gcd(mut x: u32, mut y: u32) -> u32 {
-    while y != 0 {
-        (x, y) = (y, x % y);
-    }
-    x
+/// parse durations like "250ms", "1.5s", "2m" or "1h", a bare number means seconds
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let text = input.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let scale = match unit.trim() {
+        "ms" => 0.001,
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        unit => {
+            return Err(format!(
+                "unknown unit '{}' in '{}', expected ms, s, m or h",
+                unit, input
+            ))
+        }
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", input))?;
+    Duration::try_from_secs_f64(number * scale).map_err(|e| format!("{} in '{}'", e, input))
 }
 
-pub fn mask(signals: Vec<i32>) {
+fn sigmask(how: libc::c_int, signals: &[i32]) {
     unsafe {
         let mut sigset = MaybeUninit::uninit();
         if libc::sigemptyset(sigset.as_mut_ptr()) != -1 {
             let mut sigset = sigset.assume_init();
             for signal in signals.iter() {
-                libc::sigaddset(&mut sigset, SIGRTMIN + signal);
+                libc::sigaddset(&mut sigset, *signal);
             }
-            libc::pthread_sigmask(libc::SIG_BLOCK, &sigset, ptr::null_mut());
+            libc::pthread_sigmask(how, &sigset, ptr::null_mut());
         }
     }
 }
 
+/// block signals in the calling thread, threads spawned later inherit the mask
+pub fn mask(signals: &[i32]) {
+    sigmask(libc::SIG_BLOCK, signals);
+}
+
+pub fn unmask(signals: &[i32]) {
+    sigmask(libc::SIG_UNBLOCK, signals);
+}
+
 pub fn signal(signal: i32, action: fn(i32)) {
     unsafe {
         let mut sigset = MaybeUninit::uninit();
@@ -40,9 +69,25 @@ pub fn signal(signal: i32, action: fn(i32)) {
             let mut sigaction: libc::sigaction = zeroed();
             sigaction.sa_mask = sigset.assume_init();
             sigaction.sa_sigaction = action as usize;
-            libc::sigaction(signal + SIGRTMIN, &sigaction, ptr::null_mut());
+            libc::sigaction(signal, &sigaction, ptr::null_mut());
+        }
+    }
+}
+
+/// escape text coming from outside so pango does not take it for markup
+pub fn pango_escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '\'' => result.push_str("&apos;"),
+            '"' => result.push_str("&quot;"),
+            c => result.push(c),
         }
     }
+    result
 }
 
 pub fn read_color(input: &str, default: u32) -> u32 {
@@ -56,3 +101,31 @@ pub fn read_color(input: &str, default: u32) -> u32 {
         default
     }
 }
+
+/// match name against a mask with * for any sequence of characters and ?
+/// for exactly one
+pub fn matches(mask: &str, name: &str) -> bool {
+    let m: Vec<char> = mask.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    let (mut mi, mut ni) = (0, 0);
+    let mut star = None;
+    let mut retry = 0;
+
+    while ni < n.len() {
+        if mi < m.len() && (m[mi] == '?' || m[mi] == n[ni]) {
+            mi += 1;
+            ni += 1;
+        } else if mi < m.len() && m[mi] == '*' {
+            star = Some(mi);
+            mi += 1;
+            retry = ni;
+        } else if let Some(pos) = star {
+            mi = pos + 1;
+            retry += 1;
+            ni = retry;
+        } else {
+            return false;
+        }
+    }
+    m[mi..].iter().all(|c| *c == '*')
+}
@@ -75,6 +75,17 @@ impl PipewireDevice {
     }
 }
 
+/// options of the block, Base ones excluded
+pub const FIELDS: &[&str] = &[
+    "mixer",
+    "card",
+    "jack_icons",
+    "jack_only",
+    "alsa_jack_switch_outputs",
+    "alsa_jack_mute_on_unplug",
+    "alsa_jack_unmute_on_plug",
+];
+
 #[derive(Deserialize)]
 pub struct Block {
     #[serde(flatten)]
@@ -0,0 +1,100 @@
+use std::{
+    ffi::{CString, OsString},
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+const SETTLE_DELAY_MS: u64 = 100;
+const EVENT_HEADER: usize = std::mem::size_of::<libc::inotify_event>();
+
+struct Target {
+    wd: i32,
+    name: OsString,
+}
+
+/// directory and file name of the path, and of its target if it is a symlink
+fn split(path: &Path) -> Vec<(PathBuf, OsString)> {
+    let mut result = Vec::new();
+    let canonical = path.canonicalize().ok();
+    for path in std::iter::once(path.to_path_buf()).chain(canonical) {
+        let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+            continue;
+        };
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let entry = (dir.to_path_buf(), name.to_os_string());
+        if !result.contains(&entry) {
+            result.push(entry);
+        }
+    }
+    result
+}
+
+/// call action whenever the file at path is written or replaced
+pub fn watch(path: &Path, action: fn()) {
+    let entries = split(path);
+    thread::spawn(move || unsafe {
+        let fd = libc::inotify_init1(libc::IN_CLOEXEC);
+        if fd < 0 {
+            return;
+        }
+
+        // editors tend to replace the file rather than write it in place,
+        // which a watch on the file itself would not survive
+        let mut targets = Vec::new();
+        for (dir, name) in entries {
+            let Ok(dir) = CString::new(dir.as_os_str().as_bytes()) else {
+                continue;
+            };
+            let wd =
+                libc::inotify_add_watch(fd, dir.as_ptr(), libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO);
+            if wd >= 0 {
+                targets.push(Target { wd, name });
+            }
+        }
+        if targets.is_empty() {
+            libc::close(fd);
+            return;
+        }
+
+        let mut buf = [0u8; 4096];
+        loop {
+            let read = libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len());
+            if read <= 0 {
+                if read < 0
+                    && std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted
+                {
+                    continue;
+                }
+                break;
+            }
+
+            let mut changed = false;
+            let mut offset = 0;
+            while offset + EVENT_HEADER <= read as usize {
+                let event = read_event(&buf[offset..]);
+                let name = &buf[offset + EVENT_HEADER..offset + EVENT_HEADER + event.len as usize];
+                let name = &name[..name.iter().position(|c| *c == 0).unwrap_or(name.len())];
+                changed |= targets
+                    .iter()
+                    .any(|target| target.wd == event.wd && target.name.as_bytes() == name);
+                offset += EVENT_HEADER + event.len as usize;
+            }
+
+            if changed {
+                thread::sleep(Duration::from_millis(SETTLE_DELAY_MS));
+                action();
+            }
+        }
+        libc::close(fd);
+    });
+}
+
+fn read_event(buf: &[u8]) -> libc::inotify_event {
+    unsafe { std::ptr::read_unaligned(buf.as_ptr() as *const libc::inotify_event) }
+}
@@ -0,0 +1,303 @@
+use super::blocks;
+use std::{
+    io::{BufRead, BufReader, Read},
+    os::unix::process::CommandExt,
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+static INLINE: AtomicBool = AtomicBool::new(false);
+
+/// run the jobs in the calling thread from now on, for a single status line
+/// there is nothing to render meanwhile
+pub fn run_inline() {
+    INLINE.store(true, Ordering::Release);
+}
+
+/// true once run_inline was called
+pub fn inline() -> bool {
+    INLINE.load(Ordering::Acquire)
+}
+
+struct Shared<T> {
+    started: Option<Instant>,
+    result: Option<T>,
+}
+
+/// background job of a block, its result is handed back to the block
+/// through Block::finish
+pub struct Worker<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+    /// notified once a job is done
+    done: Arc<Condvar>,
+}
+
+impl<T> Default for Worker<T> {
+    fn default() -> Self {
+        Worker {
+            shared: Arc::new(Mutex::new(Shared {
+                started: None,
+                result: None,
+            })),
+            done: Arc::new(Condvar::new()),
+        }
+    }
+}
+
+impl<T: Send + 'static> Worker<T> {
+    /// start the job unless the previous one is still running, returns
+    /// false in that case. The block is found by instance and name once the
+    /// job is done, its index may belong to another one after a reload
+    pub fn start<F>(&self, instance: String, name: String, job: F) -> bool
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        {
+            let mut shared = self.shared.lock().unwrap();
+            if shared.started.is_some() {
+                return false;
+            }
+            shared.started = Some(Instant::now());
+        }
+
+        let shared = self.shared.clone();
+        let done = self.done.clone();
+        let complete = move |result| {
+            let mut shared = shared.lock().unwrap();
+            shared.started = None;
+            shared.result = Some(result);
+            done.notify_all();
+        };
+        if inline() {
+            complete(job());
+        } else {
+            thread::spawn(move || {
+                complete(job());
+                blocks::finish_by_instance(&instance, &name);
+            });
+        }
+        true
+    }
+
+    /// result of the last job, if it is not taken yet
+    pub fn take(&self) -> Option<T> {
+        self.shared.lock().unwrap().result.take()
+    }
+
+    /// wait for the current job to be done, returns false if it is still
+    /// running after timeout
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let shared = self.shared.lock().unwrap();
+        let (shared, _) = self
+            .done
+            .wait_timeout_while(shared, timeout, |shared| shared.started.is_some())
+            .unwrap();
+        shared.started.is_none()
+    }
+
+    /// for how long the current job has been running
+    pub fn running_for(&self) -> Option<Duration> {
+        self.shared
+            .lock()
+            .unwrap()
+            .started
+            .map(|started| started.elapsed())
+    }
+}
+
+pub enum RunError {
+    Failed,
+    TimedOut,
+}
+
+/// output of a shell command
+pub struct Output {
+    pub status: ExitStatus,
+    pub stdout: Vec<u8>,
+}
+
+/// environment variables passed to a command
+pub type Environment = Vec<(String, String)>;
+
+fn command(command: &str, env: &Environment) -> Command {
+    let mut result = Command::new("sh");
+    // stdin carries the click events, the command must not consume them.
+    // A group of its own lets the whole pipeline be killed at once
+    result
+        .arg("-c")
+        .arg(command)
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .process_group(0);
+    result
+}
+
+/// run command with sh, killing it along with its children if it does not
+/// finish in time
+pub fn run(
+    command_line: &str,
+    env: &Environment,
+    timeout: Option<Duration>,
+) -> Result<Output, RunError> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut child = command(command_line, env)
+        .spawn()
+        .map_err(|_| RunError::Failed)?;
+
+    let mut stdout = child.stdout.take().ok_or(RunError::Failed)?;
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = stdout.read_to_end(&mut buf);
+        let _ = sender.send(buf);
+    });
+
+    let received = match deadline {
+        Some(deadline) => receiver
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            .ok(),
+        None => receiver.recv().ok(),
+    };
+    // stdout is closed once the command is done, yet it may linger a bit
+    let status = received.as_ref().and_then(|_| match deadline {
+        Some(deadline) => wait_until(&mut child, deadline),
+        None => child.wait().ok(),
+    });
+    match (received, status) {
+        (Some(stdout), Some(status)) => Ok(Output { status, stdout }),
+        _ => {
+            kill(&mut child);
+            Err(RunError::TimedOut)
+        }
+    }
+}
+
+fn wait_until(child: &mut Child, deadline: Instant) -> Option<ExitStatus> {
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Some(status),
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+            _ => return None,
+        }
+    }
+}
+
+fn kill(child: &mut Child) {
+    unsafe {
+        libc::kill(-(child.id() as i32), libc::SIGKILL);
+    }
+    let _ = child.wait();
+}
+
+const RESTART_DELAY_MIN: Duration = Duration::from_secs(1);
+const RESTART_DELAY_MAX: Duration = Duration::from_secs(60);
+
+struct StreamState {
+    alive: bool,
+    pid: Option<u32>,
+    line: Option<String>,
+}
+
+/// long running command of a block, every line it prints is handed back to
+/// the block through Block::finish. The command is restarted if it exits
+pub struct Stream {
+    state: Arc<Mutex<StreamState>>,
+}
+
+impl Stream {
+    pub fn start(instance: String, name: String, command: &str, env: Environment) -> Self {
+        let stream = Stream {
+            state: Arc::new(Mutex::new(StreamState {
+                alive: true,
+                pid: None,
+                line: None,
+            })),
+        };
+        let state = stream.state.clone();
+        let command = command.to_owned();
+        if INLINE.load(Ordering::Acquire) {
+            // a single status line needs a single value
+            if let Some(mut child) = spawn(&state, &command, &env) {
+                let line = child
+                    .stdout
+                    .take()
+                    .and_then(|stdout| BufReader::new(stdout).lines().next().and_then(Result::ok));
+                state.lock().unwrap().line = line;
+                kill(&mut child);
+            }
+        } else {
+            thread::spawn(move || follow(state, &instance, &name, &command, &env));
+        }
+        stream
+    }
+
+    /// the last line printed by the command, if it is not taken yet
+    pub fn take(&self) -> Option<String> {
+        self.state.lock().unwrap().line.take()
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.alive = false;
+        if let Some(pid) = state.pid.take() {
+            unsafe {
+                libc::kill(-(pid as i32), libc::SIGTERM);
+            }
+        }
+    }
+}
+
+fn spawn(state: &Mutex<StreamState>, command_line: &str, env: &Environment) -> Option<Child> {
+    let mut child = command(command_line, env).spawn().ok()?;
+    // the stream may be dropped while the command was starting
+    let mut state = state.lock().unwrap();
+    if !state.alive {
+        drop(state);
+        kill(&mut child);
+        return None;
+    }
+    state.pid = Some(child.id());
+    Some(child)
+}
+
+fn follow(
+    state: Arc<Mutex<StreamState>>,
+    instance: &str,
+    name: &str,
+    command: &str,
+    env: &Environment,
+) {
+    let mut delay = RESTART_DELAY_MIN;
+    while state.lock().unwrap().alive {
+        let started = Instant::now();
+        if let Some(mut child) = spawn(&state, command, env) {
+            if let Some(stdout) = child.stdout.take() {
+                for line in BufReader::new(stdout).lines() {
+                    let Ok(line) = line else {
+                        break;
+                    };
+                    state.lock().unwrap().line = Some(line);
+                    blocks::finish_by_instance(instance, name);
+                }
+            }
+            let _ = child.wait();
+            state.lock().unwrap().pid = None;
+        }
+
+        // a command which keeps failing is restarted less and less often
+        if started.elapsed() >= RESTART_DELAY_MAX {
+            delay = RESTART_DELAY_MIN;
+        }
+        thread::sleep(delay);
+        delay = (delay * 2).min(RESTART_DELAY_MAX);
+    }
+}
@@ -0,0 +1,129 @@
+//! The battery block with `backend: upower` against examples/fake_upower on a
+//! private session bus. Needs dbus-daemon, so it is ignored by default:
+//!
+//!   cargo test -- --ignored
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// how long a change of the fake may take to show up
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// child process killed once the test is done, even if it failed
+struct Process(Child);
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn manifest_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// examples are built next to the binary by cargo test
+fn fake_upower() -> PathBuf {
+    let rstatus = Path::new(env!("CARGO_BIN_EXE_rstatus"));
+    let fake = rstatus.with_file_name("examples").join("fake_upower");
+    assert!(fake.exists(), "{} is not built", fake.display());
+    fake
+}
+
+/// start a private session bus, returns it with its address
+fn session_bus() -> (Process, String) {
+    let mut daemon = Command::new("dbus-daemon")
+        .args(["--session", "--nofork", "--print-address"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("cannot run dbus-daemon");
+    let stdout = daemon.stdout.take().unwrap();
+    let daemon = Process(daemon);
+    let mut address = String::new();
+    BufReader::new(stdout)
+        .read_line(&mut address)
+        .expect("no bus address");
+    (daemon, address.trim().to_owned())
+}
+
+/// start the fake UPower on the bus, returns it once it owns its name
+fn start_fake(address: &str) -> (Process, ChildStdin) {
+    let mut fake = Command::new(fake_upower())
+        .env("DBUS_SESSION_BUS_ADDRESS", address)
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("cannot run fake_upower");
+    let stdin = fake.stdin.take().unwrap();
+    let stderr = fake.stderr.take().unwrap();
+    let fake = Process(fake);
+    let mut line = String::new();
+    BufReader::new(stderr)
+        .read_line(&mut line)
+        .expect("fake_upower is not ready");
+    assert_eq!(line.trim(), "fake_upower: ready");
+    (fake, stdin)
+}
+
+/// a single status line of samples/upower.yaml
+fn status_line(address: &str) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_rstatus"))
+        .current_dir(manifest_dir())
+        .args(["-c", "samples/upower.yaml", "--once"])
+        .env("DBUS_SESSION_BUS_ADDRESS", address)
+        .output()
+        .expect("cannot run rstatus");
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// the status line once it contains text, the fake applies commands a bit
+/// later than they are written
+fn wait_for(address: &str, text: &str) -> String {
+    let start = Instant::now();
+    loop {
+        let line = status_line(address);
+        if line.contains(text) {
+            return line;
+        }
+        assert!(
+            start.elapsed() < TIMEOUT,
+            "'{}' is not in the status line {}",
+            text,
+            line
+        );
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+#[ignore = "needs dbus-daemon, run with --ignored"]
+fn once_follows_fake_upower() {
+    let (_daemon, address) = session_bus();
+
+    // the battery is invalid without UPower
+    assert!(status_line(&address).contains("\"full_text\":\"invalid\""));
+
+    let (_fake, mut commands) = start_fake(&address);
+    let line = status_line(&address);
+    assert!(
+        line.contains("\"full_text\":\"75% 3:00 12.5W offline\""),
+        "{}",
+        line
+    );
+
+    writeln!(commands, "percentage 40").unwrap();
+    wait_for(&address, "\"full_text\":\"40% 1:36 12.5W offline\"");
+
+    writeln!(commands, "ac online").unwrap();
+    wait_for(&address, "\"full_text\":\"40% 1:36 12.5W online\"");
+
+    // the low percentage turns the block red
+    writeln!(commands, "percentage 10").unwrap();
+    let line = wait_for(&address, "\"full_text\":\"10% ");
+    assert!(line.contains("\"color\":\"#FF0000\""), "{}", line);
+}